@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use serde_json::json;
+
+use crate::cache;
+use crate::config;
+use crate::error;
+
+// Tokens this close to expiring are treated as expired
+const TOKEN_EXPIRY_MARGIN: i64 = 60;
+
+#[derive(Clone)]
+pub struct Account {
+    pub inquiry_code: String,
+    pub password: String,
+    pub tokens: HashMap<String, String>,
+}
+
+// The account is a credential, so it lives in the data directory rather than the cache
+fn get_account_path() -> PathBuf {
+    if let Some(data_dir) = &config::get().data_dir {
+        return data_dir.join("account.json");
+    }
+    match dirs::data_dir() {
+        Some(data_dir) => data_dir.join("bc-gatya-seeker").join("account.json"),
+        None => cache::get_data_path("account.json"),
+    }
+}
+
+// Where the account was kept before it moved out of the cache
+fn get_legacy_account_path() -> PathBuf {
+    cache::get_data_path("account.json")
+}
+
+fn read_account_file(file_path: &Path) -> Option<Account> {
+    let data: String = std::fs::read_to_string(file_path).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&data).ok()?;
+    let mut tokens: HashMap<String, String> = HashMap::new();
+    if let Some(token_map) = json["tokens"].as_object() {
+        for (cc, token) in token_map.iter() {
+            if let Some(token) = token.as_str() {
+                tokens.insert(cc.to_string(), token.to_string());
+            }
+        }
+    }
+    Some(Account {
+        inquiry_code: json["inquiry_code"].as_str()?.to_string(),
+        password: json["password"].as_str()?.to_string(),
+        tokens,
+    })
+}
+
+pub fn load_account() -> Option<Account> {
+    let account_path: PathBuf = get_account_path();
+    if account_path.exists() {
+        return read_account_file(&account_path);
+    }
+    read_account_file(&get_legacy_account_path())
+}
+
+// Only the owner can read the file on Unix, since it holds the account password
+fn write_private_file(file_path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut options: std::fs::OpenOptions = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // the mode is only used when the file is created
+        if file_path.exists() {
+            std::fs::set_permissions(file_path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(file_path)?.write_all(data)
+}
+
+fn write_account_file(file_path: &Path, account: &Account) -> error::Result<()> {
+    let data: serde_json::Value = json!({
+        "inquiry_code": account.inquiry_code,
+        "password": account.password,
+        "tokens": account.tokens,
+    });
+    if let Some(parent) = file_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    write_private_file(file_path, data.to_string().as_bytes())?;
+    Ok(())
+}
+
+pub fn save_account(account: &Account) -> error::Result<()> {
+    let account_path: PathBuf = get_account_path();
+    write_account_file(&account_path, account)?;
+    let legacy_path: PathBuf = get_legacy_account_path();
+    if legacy_path != account_path && legacy_path.exists() {
+        std::fs::remove_file(legacy_path)?;
+    }
+    Ok(())
+}
+
+// Reads the exp claim from a JWT without checking its signature
+fn get_token_expiry(token: &str) -> Option<i64> {
+    let payload: &str = token.split('.').nth(1)?;
+    let payload: Vec<u8> = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()?;
+    let json: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+    json["exp"].as_i64()
+}
+
+pub fn is_token_valid(token: &str, now: i64) -> bool {
+    match get_token_expiry(token) {
+        Some(expiry) => expiry - TOKEN_EXPIRY_MARGIN > now,
+        None => false,
+    }
+}
+
+impl Account {
+    pub fn get_valid_token(&self, cc: &str, now: i64) -> Option<String> {
+        let token: &String = self.tokens.get(cc)?;
+        if is_token_valid(token, now) {
+            return Some(token.clone());
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_account() -> Account {
+        Account {
+            inquiry_code: "abc123".to_string(),
+            password: "secret".to_string(),
+            tokens: HashMap::from([("en".to_string(), "token".to_string())]),
+        }
+    }
+
+    fn get_token(payload: &str) -> String {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        format!(
+            "{}.{}.signature",
+            engine.encode(r#"{"alg":"HS256"}"#),
+            engine.encode(payload)
+        )
+    }
+
+    #[test]
+    fn round_trips_the_account_file() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let file_path: PathBuf = dir.path().join("nested").join("account.json");
+        write_account_file(&file_path, &get_test_account()).unwrap();
+        let account: Account = read_account_file(&file_path).unwrap();
+        assert_eq!(account.inquiry_code, "abc123");
+        assert_eq!(account.password, "secret");
+        assert_eq!(account.tokens["en"], "token");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn account_file_is_only_readable_by_the_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let file_path: PathBuf = dir.path().join("account.json");
+        std::fs::write(&file_path, "{}").unwrap();
+        std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        write_account_file(&file_path, &get_test_account()).unwrap();
+        let mode: u32 = std::fs::metadata(&file_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn checks_token_expiry() {
+        let token: String = get_token(r#"{"exp":1000}"#);
+        assert!(is_token_valid(&token, 900));
+        assert!(!is_token_valid(&token, 950));
+        assert!(!is_token_valid(&get_token(r#"{"sub":"x"}"#), 0));
+        assert!(!is_token_valid("not a token", 0));
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use crate::config;
+use crate::error;
+
+pub struct CacheMetadata {
+    pub version: Option<String>,
+    pub fetched_at: Option<DateTime<Utc>>,
+}
+
+// Falls back to a local data folder if the platform has no cache directory
+pub fn get_data_dir() -> PathBuf {
+    if let Some(data_dir) = &config::get().data_dir {
+        return data_dir.clone();
+    }
+    match dirs::cache_dir() {
+        Some(cache_dir) => cache_dir.join("bc-gatya-seeker"),
+        None => PathBuf::from("data"),
+    }
+}
+
+// Caches used to be kept in `data/` in the working directory
+pub fn check_legacy_data_dir() {
+    let legacy_dir: &Path = Path::new("data");
+    if !legacy_dir.is_dir() || get_data_dir() == legacy_dir {
+        return;
+    }
+    let has_cache_files: bool = std::fs::read_dir(legacy_dir)
+        .map(|entries| {
+            entries.filter_map(|entry| entry.ok()).any(|entry| {
+                let file_name: String = entry.file_name().to_string_lossy().to_string();
+                (file_name.starts_with("gatya_") || file_name.starts_with("unitbuy_"))
+                    && (file_name.ends_with(".csv") || file_name.ends_with(".tsv"))
+            })
+        })
+        .unwrap_or(false);
+    if has_cache_files {
+        eprintln!(
+            "The old cache in {} is no longer used, data is now kept in {}. It can be deleted.",
+            legacy_dir.display(),
+            get_data_dir().display()
+        );
+    }
+}
+
+pub fn get_data_path(file_name: &str) -> PathBuf {
+    get_data_dir().join(file_name)
+}
+
+fn get_metadata_path(file_path: &Path) -> PathBuf {
+    let mut file_name = file_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".meta.json");
+    file_path.with_file_name(file_name)
+}
+
+pub fn read_metadata(file_path: &Path) -> Option<CacheMetadata> {
+    let data: String = std::fs::read_to_string(get_metadata_path(file_path)).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&data).ok()?;
+    let fetched_at: Option<DateTime<Utc>> = json["fetched_at"]
+        .as_str()
+        .and_then(|fetched_at| DateTime::parse_from_rfc3339(fetched_at).ok())
+        .map(|fetched_at| fetched_at.with_timezone(&Utc));
+    Some(CacheMetadata {
+        version: json["version"].as_str().map(|version| version.to_string()),
+        fetched_at,
+    })
+}
+
+pub fn read_cached(file_path: &Path) -> error::Result<String> {
+    Ok(std::fs::read_to_string(file_path)?)
+}
+
+pub fn write_cached(file_path: &Path, data: &[u8], version: Option<&str>) -> error::Result<()> {
+    if let Some(parent) = file_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(file_path, data)?;
+    let metadata: serde_json::Value = json!({
+        "version": version,
+        "fetched_at": Utc::now().to_rfc3339(),
+    });
+    std::fs::write(get_metadata_path(file_path), metadata.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::cache;
+use crate::config;
+use crate::error;
+use crate::gatya_data;
+use crate::http;
+
+const FUZZY_THRESHOLD: f64 = 0.6;
+// Name downloads running at once, so a big banner doesn't flood the server
+const MAX_DOWNLOADS: usize = 8;
+
+fn get_lang_code(cc: &str) -> &str {
+    match cc {
+        "jp" => "ja",
+        "kr" => "ko",
+        _ => cc,
+    }
+}
+
+fn read_names_file(file_path: &Path) -> HashMap<i32, String> {
+    let mut names: HashMap<i32, String> = HashMap::new();
+    let data: String = match std::fs::read_to_string(file_path) {
+        Ok(data) => data,
+        Err(_) => return names,
+    };
+    for line in data.lines() {
+        let (cat_id, name) = match line.split_once('\t') {
+            Some(parts) => parts,
+            None => continue,
+        };
+        if let Ok(cat_id) = cat_id.trim().parse::<i32>() {
+            names.insert(cat_id, name.trim().to_string());
+        }
+    }
+    names
+}
+
+pub fn get_cached_cat_names(cc: &str) -> HashMap<i32, String> {
+    read_names_file(&cache::get_data_path(&format!("names_{}.tsv", cc)))
+}
+
+fn write_names_file(
+    file_path: &Path,
+    names: &HashMap<i32, String>,
+    version: &str,
+) -> error::Result<()> {
+    let mut cat_ids: Vec<&i32> = names.keys().collect();
+    cat_ids.sort();
+    let mut data: String = String::new();
+    for cat_id in cat_ids {
+        data.push_str(&format!("{}\t{}\n", cat_id, names[cat_id]));
+    }
+    cache::write_cached(file_path, data.as_bytes(), Some(version))?;
+    Ok(())
+}
+
+// The first field of a unit explanation file is the name of the unit's first form
+fn parse_unit_explanation(data: &str) -> Option<String> {
+    let first_line: &str = data.lines().next()?;
+    let delimiter: char = if first_line.contains('|') { '|' } else { ',' };
+    let name: &str = first_line.split(delimiter).next()?.trim();
+    if name.is_empty() {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+async fn download_name(url: String) -> Option<String> {
+    let body: String = http::get_text(http::get_client().get(&url)).await.ok()?;
+    parse_unit_explanation(&body)
+}
+
+pub async fn get_cat_names(
+    cc: &str,
+    cat_ids: &[i32],
+    force: bool,
+) -> error::Result<HashMap<i32, String>> {
+    let file_path: PathBuf = cache::get_data_path(&format!("names_{}.tsv", cc));
+    let mut names: HashMap<i32, String> = if force {
+        HashMap::new()
+    } else {
+        read_names_file(&file_path)
+    };
+
+    // names are fetched again when BCData has a newer game version than the cached ones
+    let latest_game_data_version: String = match gatya_data::get_latest_game_data_version(cc).await
+    {
+        Ok(version) => version,
+        Err(err) if !names.is_empty() => {
+            eprintln!(
+                "Could not check for new cat names, using cached names. {}",
+                err
+            );
+            return Ok(names);
+        }
+        Err(err) => return Err(err),
+    };
+    let cached_version: Option<String> =
+        cache::read_metadata(&file_path).and_then(|metadata| metadata.version);
+    if cached_version.as_deref() != Some(latest_game_data_version.as_str()) {
+        names.clear();
+    }
+
+    let missing: Vec<i32> = cat_ids
+        .iter()
+        .filter(|cat_id| !names.contains_key(cat_id))
+        .copied()
+        .collect();
+    if missing.is_empty() {
+        return Ok(names);
+    }
+
+    eprintln!("Getting cat names...");
+    let semaphore: Arc<Semaphore> = Arc::new(Semaphore::new(MAX_DOWNLOADS));
+    let mut tasks: JoinSet<(i32, Option<String>)> = JoinSet::new();
+    for cat_id in missing {
+        let url: String = format!(
+            "{}/{}/resLocal/Unit_Explanation{}_{}.csv",
+            config::get().bcdata_url,
+            latest_game_data_version,
+            cat_id + 1,
+            get_lang_code(cc)
+        );
+        let semaphore: Arc<Semaphore> = semaphore.clone();
+        tasks.spawn(async move {
+            // the semaphore is never closed, so acquiring can't fail
+            let _permit = semaphore.acquire_owned().await.ok();
+            (cat_id, download_name(url).await)
+        });
+    }
+    while let Some(result) = tasks.join_next().await {
+        if let Ok((cat_id, Some(name))) = result {
+            names.insert(cat_id, name);
+        }
+    }
+
+    write_names_file(&file_path, &names, &latest_game_data_version)?;
+    Ok(names)
+}
+
+pub fn get_cat_display(cat_id: i32, names: &HashMap<i32, String>) -> String {
+    match names.get(&cat_id) {
+        Some(name) => format!("{} ({})", name, cat_id),
+        None => format!("cat {}", cat_id),
+    }
+}
+
+// Exact matches win, then partial matches, then the closest names by edit distance
+pub fn find_cats_by_name(query: &str, names: &HashMap<i32, String>, cat_ids: &[i32]) -> Vec<i32> {
+    let query: String = query.trim().to_lowercase();
+    let candidates: Vec<(i32, String)> = cat_ids
+        .iter()
+        .filter_map(|cat_id| names.get(cat_id).map(|name| (*cat_id, name.to_lowercase())))
+        .collect();
+
+    let exact: Vec<i32> = candidates
+        .iter()
+        .filter(|(_, name)| *name == query)
+        .map(|(cat_id, _)| *cat_id)
+        .collect();
+    if !exact.is_empty() {
+        return exact;
+    }
+
+    let partial: Vec<i32> = candidates
+        .iter()
+        .filter(|(_, name)| name.contains(&query))
+        .map(|(cat_id, _)| *cat_id)
+        .collect();
+    if !partial.is_empty() {
+        return partial;
+    }
+
+    let mut best_score: f64 = FUZZY_THRESHOLD;
+    let mut fuzzy: Vec<i32> = Vec::new();
+    for (cat_id, name) in candidates.iter() {
+        let score: f64 = strsim::normalized_levenshtein(&query, name);
+        if score > best_score {
+            best_score = score;
+            fuzzy = vec![*cat_id];
+        } else if score == best_score && !fuzzy.is_empty() {
+            fuzzy.push(*cat_id);
+        }
+    }
+    fuzzy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_unit_explanations() {
+        assert_eq!(
+            parse_unit_explanation("Cat|Cat's first form\n").as_deref(),
+            Some("Cat")
+        );
+        assert_eq!(
+            parse_unit_explanation("Tank Cat,A wall\n").as_deref(),
+            Some("Tank Cat")
+        );
+        assert_eq!(parse_unit_explanation("|empty\n"), None);
+        assert_eq!(parse_unit_explanation(""), None);
+    }
+
+    #[test]
+    fn finds_cats_by_name() {
+        let names: HashMap<i32, String> = [(1, "Tank Cat"), (2, "Axe Cat"), (3, "Cat")]
+            .iter()
+            .map(|(cat_id, name)| (*cat_id, name.to_string()))
+            .collect();
+        let cat_ids: [i32; 3] = [1, 2, 3];
+        assert_eq!(find_cats_by_name("cat", &names, &cat_ids), vec![3]);
+        let mut partial: Vec<i32> = find_cats_by_name("tank", &names, &cat_ids);
+        partial.sort();
+        assert_eq!(partial, vec![1]);
+        assert_eq!(find_cats_by_name("Axe Kat", &names, &cat_ids), vec![2]);
+        assert!(find_cats_by_name("Bahamut", &names, &cat_ids).is_empty());
+    }
+}
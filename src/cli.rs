@@ -0,0 +1,226 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+// Anything left out is asked for with the interactive prompts instead
+#[derive(Parser)]
+#[command(version, about = "Find and simulate Battle Cats rare gacha seeds")]
+pub struct Cli {
+    /// Only use imported or cached data
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Run against a local mock server instead of the real servers
+    #[arg(long, global = true)]
+    pub mock: bool,
+    /// Game version sent to the server, like 130100, instead of the one worked out from BCData
+    #[arg(long, global = true, value_parser = parse_client_version)]
+    pub client_version: Option<String>,
+    /// Only warn when a server response has a missing or wrong signature instead of failing
+    #[arg(long, global = true)]
+    pub skip_signature_check: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+fn parse_client_version(value: &str) -> Result<String, String> {
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+        return Err("expected only digits, like 130100".to_string());
+    }
+    Ok(value.to_string())
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List gacha events
+    Events(EventsArgs),
+    /// Find your seed from the cats you rolled
+    Find(FindArgs),
+    /// Find your seed from the rarities you rolled
+    Seek(SeekArgs),
+    /// Show the rolls for a seed
+    Simulate(SimulateArgs),
+    /// Run pattern files and write a result file for each one
+    Batch(BatchArgs),
+    /// Browse banners, pick cats and search for seeds in a full-screen interface
+    Tui(TuiArgs),
+    /// Download the latest event and game data
+    UpdateData(RegionArgs),
+    /// Import game data from a directory, zip, apk or DataLocal.pack
+    Import { path: String, cc: String },
+    /// Run the mock server on its own
+    MockServer {
+        #[arg(default_value_t = 8080)]
+        port: u16,
+    },
+}
+
+#[derive(Args, Clone)]
+pub struct RegionArgs {
+    /// Country code: en, jp, kr or tw
+    #[arg(long, short)]
+    pub region: Option<String>,
+}
+
+#[derive(Args, Clone)]
+pub struct EventArgs {
+    #[command(flatten)]
+    pub region: RegionArgs,
+    /// Gatya ID of the banner
+    #[arg(long, short)]
+    pub gatya: Option<u32>,
+    /// Download the latest event and game data first
+    #[arg(long)]
+    pub update: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Args, Clone)]
+pub struct OutputArgs {
+    /// Output format, json and csv include every seed
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StatusArg {
+    Running,
+    Upcoming,
+    Past,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SortArg {
+    File,
+    Start,
+    End,
+    Id,
+}
+
+#[derive(Args)]
+pub struct EventsArgs {
+    #[command(flatten)]
+    pub region: RegionArgs,
+    #[arg(long)]
+    pub status: Option<StatusArg>,
+    /// Only show banners containing this text
+    #[arg(long)]
+    pub text: Option<String>,
+    #[arg(long, short)]
+    pub gatya: Option<u32>,
+    #[arg(long, value_enum, default_value_t = SortArg::File)]
+    pub sort: SortArg,
+    /// Include events from every saved snapshot of the event data
+    #[arg(long)]
+    pub snapshots: bool,
+    /// Download the latest event data first
+    #[arg(long)]
+    pub update: bool,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DrawArg {
+    Single,
+    Eleven,
+    StepUp,
+}
+
+#[derive(Args, Clone)]
+pub struct DrawArgs {
+    /// How the cats were rolled, asked for on guaranteed and step-up banners if left out
+    #[arg(long)]
+    pub draw: Option<DrawArg>,
+    /// Step-up step the first cat was rolled on
+    #[arg(long, default_value_t = 1)]
+    pub step: usize,
+    /// Step-up steps as ROLLS:PRICE, ending in :uber for a guaranteed uber,
+    /// like 11:750,11:1500,11:1500,11:1500:uber (the layout assumed if left out)
+    #[arg(long)]
+    pub steps: Option<String>,
+}
+
+#[derive(Args)]
+pub struct FindArgs {
+    #[command(flatten)]
+    pub event: EventArgs,
+    /// Cats in the order they were rolled: IDs, names or #numbers from the pool, -2 for a blank
+    #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+    pub cats: Option<Vec<String>>,
+    #[command(flatten)]
+    pub draw: DrawArgs,
+    /// Threads to search with, defaults to the number of CPUs
+    #[arg(long, short)]
+    pub threads: Option<u32>,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct SeekArgs {
+    #[command(flatten)]
+    pub event: EventArgs,
+    /// Rarities in the order they were rolled: rare, super, uber, legend or blank (or 1-4, -2)
+    #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+    pub rarities: Option<Vec<String>>,
+    #[command(flatten)]
+    pub draw: DrawArgs,
+    /// Threads to search with, defaults to the number of CPUs
+    #[arg(long, short)]
+    pub threads: Option<u32>,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct SimulateArgs {
+    #[command(flatten)]
+    pub event: EventArgs,
+    /// Seed to roll from
+    #[arg(long, short)]
+    pub seed: Option<u32>,
+    /// Number of rolls to show
+    #[arg(long, short, default_value_t = 10)]
+    pub count: usize,
+    #[command(flatten)]
+    pub draw: DrawArgs,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum BatchFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Args)]
+pub struct BatchArgs {
+    /// Pattern files, or directories containing them
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+    /// Directory to write results to, defaults to next to each pattern file
+    #[arg(long, short)]
+    pub out_dir: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = BatchFormat::Json)]
+    pub format: BatchFormat,
+}
+
+#[derive(Args)]
+pub struct TuiArgs {
+    #[command(flatten)]
+    pub region: RegionArgs,
+    /// Download the latest event and game data first
+    #[arg(long)]
+    pub update: bool,
+    /// Threads to search with, defaults to the number of CPUs
+    #[arg(long, short)]
+    pub threads: Option<u32>,
+    /// Step-up steps as ROLLS:PRICE, ending in :uber for a guaranteed uber
+    #[arg(long)]
+    pub steps: Option<String>,
+}
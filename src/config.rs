@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+#[derive(Clone)]
+pub struct Config {
+    pub backups_url: String,
+    pub auth_url: String,
+    pub events_url: String,
+    pub bcdata_url: String,
+    pub data_dir: Option<PathBuf>,
+    pub client_version: Option<String>,
+    pub verify_signatures: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            backups_url: "https://nyanko-backups.ponosgames.com".to_string(),
+            auth_url: "https://nyanko-auth.ponosgames.com".to_string(),
+            events_url: "https://nyanko-events.ponosgames.com".to_string(),
+            bcdata_url: "https://raw.githubusercontent.com/fieryhenry/BCData/master".to_string(),
+            data_dir: None,
+            client_version: None,
+            verify_signatures: true,
+        }
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+pub fn get_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|config_dir| config_dir.join("bc-gatya-seeker").join("config.json"))
+}
+
+fn set_string(value: &mut String, json: &serde_json::Value, key: &str, env_var: &str) {
+    if let Some(new_value) = json[key].as_str() {
+        *value = new_value.to_string();
+    }
+    if let Ok(new_value) = std::env::var(env_var) {
+        *value = new_value;
+    }
+    while value.ends_with('/') {
+        value.pop();
+    }
+}
+
+// Settings come from config.json, then environment variables, then the defaults
+pub fn load_config() -> Config {
+    let mut config: Config = Config::default();
+    let json: serde_json::Value = get_config_path()
+        .and_then(|config_path| std::fs::read_to_string(config_path).ok())
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or(serde_json::Value::Null);
+
+    set_string(
+        &mut config.backups_url,
+        &json,
+        "backups_url",
+        "BC_SEEKER_BACKUPS_URL",
+    );
+    set_string(
+        &mut config.auth_url,
+        &json,
+        "auth_url",
+        "BC_SEEKER_AUTH_URL",
+    );
+    set_string(
+        &mut config.events_url,
+        &json,
+        "events_url",
+        "BC_SEEKER_EVENTS_URL",
+    );
+    set_string(
+        &mut config.bcdata_url,
+        &json,
+        "bcdata_url",
+        "BC_SEEKER_BCDATA_URL",
+    );
+
+    if let Some(data_dir) = json["data_dir"].as_str() {
+        config.data_dir = Some(PathBuf::from(data_dir));
+    }
+    if let Ok(data_dir) = std::env::var("BC_SEEKER_DATA_DIR") {
+        config.data_dir = Some(PathBuf::from(data_dir));
+    }
+
+    if let Some(client_version) = json["client_version"].as_str() {
+        config.client_version = Some(client_version.to_string());
+    }
+    if let Ok(client_version) = std::env::var("BC_SEEKER_CLIENT_VERSION") {
+        config.client_version = Some(client_version);
+    }
+
+    if let Some(verify_signatures) = json["verify_signatures"].as_bool() {
+        config.verify_signatures = verify_signatures;
+    }
+    if let Ok(verify_signatures) = std::env::var("BC_SEEKER_VERIFY_SIGNATURES") {
+        config.verify_signatures = !matches!(verify_signatures.as_str(), "0" | "false");
+    }
+    config
+}
+
+// Must be called before the first `get`, otherwise the loaded config is kept
+pub fn set(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(load_config)
+}
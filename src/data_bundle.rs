@@ -0,0 +1,319 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::cache;
+use crate::error::{self, Error};
+use crate::game_pack;
+use crate::gatya_data::GatyaType;
+
+// Files a data bundle has to contain and where they are stored for a country code
+pub fn get_bundle_files(cc: &str) -> Vec<(&'static str, PathBuf)> {
+    vec![
+        (
+            "gatya.tsv",
+            cache::get_data_path(&format!("gatya_{}.tsv", cc)),
+        ),
+        (
+            "GatyaDataSetR1.csv",
+            cache::get_data_path(&format!("gatya_{}.csv", cc)),
+        ),
+        (
+            "unitbuy.csv",
+            cache::get_data_path(&format!("unitbuy_{}.csv", cc)),
+        ),
+    ]
+}
+
+// Datasets for normal and event capsules, which are imported when present but not required
+pub fn get_optional_bundle_files(cc: &str) -> Vec<(&'static str, PathBuf)> {
+    vec![
+        (
+            "GatyaDataSetN1.csv",
+            cache::get_data_path(&format!("gatya_n1_{}.csv", cc)),
+        ),
+        (
+            "GatyaDataSetE1.csv",
+            cache::get_data_path(&format!("gatya_e1_{}.csv", cc)),
+        ),
+    ]
+}
+
+pub fn get_missing_files(cc: &str) -> Vec<String> {
+    get_bundle_files(cc)
+        .into_iter()
+        .filter(|(_, file_path)| !file_path.exists())
+        .map(|(file_name, _)| file_name.to_string())
+        .collect()
+}
+
+pub fn check_offline_data(cc: &str) -> error::Result<()> {
+    let missing: Vec<String> = get_missing_files(cc);
+    if !missing.is_empty() {
+        return Err(Error::MissingData(missing));
+    }
+    Ok(())
+}
+
+// The capsule dataset an offline banner needs, since only the rare one is required when importing
+pub fn check_offline_dataset(cc: &str, gatya_type: GatyaType) -> error::Result<()> {
+    match (gatya_type.get_dataset_name(), gatya_type.get_local_name(cc)) {
+        (Some(dataset_name), Some(local_name)) if !cache::get_data_path(&local_name).exists() => {
+            Err(Error::MissingData(vec![dataset_name.to_string()]))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn find_file_in_dir(dir: &Path, file_name: &str) -> error::Result<Option<PathBuf>> {
+    let candidate: PathBuf = dir.join(file_name);
+    if candidate.is_file() {
+        return Ok(Some(candidate));
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path: PathBuf = entry?.path();
+        if path.is_dir() {
+            if let Some(found) = find_file_in_dir(&path, file_name)? {
+                return Ok(Some(found));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn read_file_from_archive(path: &Path, file_name: &str) -> error::Result<Option<Vec<u8>>> {
+    let file: std::fs::File = std::fs::File::open(path)?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|err| Error::Parse(format!("{} is not a zip archive: {}", path.display(), err)))?;
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|err| Error::Parse(format!("bad entry in {}: {}", path.display(), err)))?;
+        let matches: bool = Path::new(entry.name())
+            .file_name()
+            .map(|name| name == file_name)
+            .unwrap_or(false);
+        if !matches || entry.is_dir() {
+            continue;
+        }
+        let mut data: Vec<u8> = Vec::new();
+        entry.read_to_end(&mut data)?;
+        return Ok(Some(data));
+    }
+    Ok(None)
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => extensions.contains(&extension.to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+fn read_bundle_file(path: &Path, file_name: &str) -> error::Result<Option<Vec<u8>>> {
+    if path.is_dir() {
+        return match find_file_in_dir(path, file_name)? {
+            Some(found) => Ok(Some(std::fs::read(found)?)),
+            None => Ok(None),
+        };
+    }
+    // a lone .pack or .list only holds game files through the pair itself
+    if has_extension(path, &["pack", "list"]) {
+        let found: bool = path
+            .file_name()
+            .map(|name| name == file_name)
+            .unwrap_or(false);
+        if !found {
+            return Ok(None);
+        }
+        return Ok(Some(std::fs::read(path)?));
+    }
+    read_file_from_archive(path, file_name)
+}
+
+// Looks for the DataLocal .list/.pack pair in a directory, an APK, or next to the given file
+fn read_data_local_pack(path: &Path) -> error::Result<Option<(Vec<u8>, Vec<u8>)>> {
+    let (list_data, pack_data) = if has_extension(path, &["pack", "list"]) {
+        (
+            std::fs::read(path.with_extension("list")).ok(),
+            std::fs::read(path.with_extension("pack")).ok(),
+        )
+    } else {
+        (
+            read_bundle_file(path, "DataLocal.list")?,
+            read_bundle_file(path, "DataLocal.pack")?,
+        )
+    };
+    match (list_data, pack_data) {
+        (Some(list_data), Some(pack_data)) => Ok(Some((list_data, pack_data))),
+        _ => Ok(None),
+    }
+}
+
+// Copies the bundle's files into the data folder, returning the names of any required ones that were missing.
+// Game data files that aren't in the bundle as-is are extracted from a DataLocal pack if there is one
+pub fn import_bundle(path: &str, cc: &str) -> error::Result<Vec<String>> {
+    let path: &Path = Path::new(path);
+    if !path.exists() {
+        return Err(Error::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} does not exist", path.display()),
+        )));
+    }
+
+    let mut found: Vec<(&str, PathBuf, Vec<u8>)> = Vec::new();
+    let mut not_found: Vec<(&str, PathBuf)> = Vec::new();
+    for (file_name, file_path) in get_bundle_files(cc)
+        .into_iter()
+        .chain(get_optional_bundle_files(cc))
+    {
+        match read_bundle_file(path, file_name)? {
+            Some(data) => found.push((file_name, file_path, data)),
+            None => not_found.push((file_name, file_path)),
+        }
+    }
+
+    if !not_found.is_empty() {
+        if let Some((list_data, pack_data)) = read_data_local_pack(path)? {
+            let file_names: Vec<&str> = not_found.iter().map(|(file_name, _)| *file_name).collect();
+            let mut extracted = game_pack::extract_files(&list_data, &pack_data, cc, &file_names)?;
+            println!("Extracted {} files from DataLocal.pack", extracted.len());
+            not_found.retain(
+                |(file_name, file_path)| match extracted.remove(*file_name) {
+                    Some(data) => {
+                        found.push((file_name, file_path.clone(), data));
+                        false
+                    }
+                    None => true,
+                },
+            );
+        }
+    }
+
+    for (file_name, file_path, data) in found {
+        cache::write_cached(&file_path, &data, Some("import"))?;
+        println!("Imported {} to {}", file_name, file_path.display());
+    }
+    let required: Vec<&str> = get_bundle_files(cc)
+        .into_iter()
+        .map(|(file_name, _)| file_name)
+        .collect();
+    Ok(not_found
+        .into_iter()
+        .filter(|(file_name, _)| required.contains(file_name))
+        .map(|(file_name, _)| file_name.to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::mock_server::tests::start_test_server;
+
+    fn write_file(path: &Path, data: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, data).unwrap();
+    }
+
+    // Imports land in the test server's shared data folder, so each test uses its own country code
+    fn read_imported(local_name: &str) -> String {
+        std::fs::read_to_string(cache::get_data_path(local_name)).unwrap()
+    }
+
+    #[test]
+    fn finds_files_in_nested_directories() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        write_file(
+            &dir.path().join("a").join("b").join("unitbuy.csv"),
+            "nested",
+        );
+        write_file(&dir.path().join("z").join("gatya.tsv"), "nested");
+        write_file(&dir.path().join("gatya.tsv"), "top");
+
+        let found: PathBuf = find_file_in_dir(dir.path(), "unitbuy.csv")
+            .unwrap()
+            .unwrap();
+        assert_eq!(found, dir.path().join("a").join("b").join("unitbuy.csv"));
+        // a file at the top wins over nested ones
+        let found: PathBuf = find_file_in_dir(dir.path(), "gatya.tsv").unwrap().unwrap();
+        assert_eq!(found, dir.path().join("gatya.tsv"));
+        assert!(find_file_in_dir(dir.path(), "GatyaDataSetR1.csv")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn imports_a_directory_bundle() {
+        start_test_server();
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        write_file(&dir.path().join("gatya.tsv"), "events");
+        write_file(&dir.path().join("csv").join("GatyaDataSetR1.csv"), "rare");
+        write_file(&dir.path().join("csv").join("GatyaDataSetN1.csv"), "normal");
+        write_file(
+            &dir.path().join("csv").join("data").join("unitbuy.csv"),
+            "units",
+        );
+
+        let missing: Vec<String> = import_bundle(dir.path().to_str().unwrap(), "xa").unwrap();
+        assert!(missing.is_empty());
+        assert_eq!(read_imported("gatya_xa.tsv"), "events");
+        assert_eq!(read_imported("gatya_xa.csv"), "rare");
+        assert_eq!(read_imported("gatya_n1_xa.csv"), "normal");
+        assert_eq!(read_imported("unitbuy_xa.csv"), "units");
+
+        check_offline_data("xa").unwrap();
+        check_offline_dataset("xa", GatyaType::Rare).unwrap();
+        check_offline_dataset("xa", GatyaType::Normal).unwrap();
+        check_offline_dataset("xa", GatyaType::Other(9)).unwrap();
+        match check_offline_dataset("xa", GatyaType::Event) {
+            Err(Error::MissingData(files)) => assert_eq!(files, vec!["GatyaDataSetE1.csv"]),
+            _ => panic!("expected the event dataset to be missing"),
+        }
+    }
+
+    #[test]
+    fn imports_a_zip_bundle() {
+        start_test_server();
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let zip_path: PathBuf = dir.path().join("bundle.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, data) in [
+            ("assets/gatya.tsv", "events"),
+            ("assets/csv/GatyaDataSetR1.csv", "rare"),
+            ("unitbuy.csv", "units"),
+        ] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let missing: Vec<String> = import_bundle(zip_path.to_str().unwrap(), "xb").unwrap();
+        assert!(missing.is_empty());
+        assert_eq!(read_imported("gatya_xb.tsv"), "events");
+        assert_eq!(read_imported("gatya_xb.csv"), "rare");
+        assert_eq!(read_imported("unitbuy_xb.csv"), "units");
+        check_offline_data("xb").unwrap();
+    }
+
+    #[test]
+    fn reports_missing_required_files() {
+        start_test_server();
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        write_file(&dir.path().join("gatya.tsv"), "events");
+        write_file(&dir.path().join("GatyaDataSetR1.csv"), "rare");
+
+        match check_offline_data("xc") {
+            Err(Error::MissingData(files)) => assert_eq!(files.len(), 3),
+            _ => panic!("expected nothing to be imported yet"),
+        }
+        let missing: Vec<String> = import_bundle(dir.path().to_str().unwrap(), "xc").unwrap();
+        assert_eq!(missing, vec!["unitbuy.csv"]);
+        match check_offline_data("xc") {
+            Err(Error::MissingData(files)) => assert_eq!(files, vec!["unitbuy.csv"]),
+            _ => panic!("expected unitbuy.csv to be missing"),
+        }
+        assert!(import_bundle(&dir.path().join("nope").to_string_lossy(), "xc").is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Network(reqwest::Error),
+    Http {
+        status: u16,
+        url: String,
+        message: String,
+    },
+    Auth(String),
+    Parse(String),
+    Io(std::io::Error),
+    Verification(String),
+    InvalidInput(String),
+    MissingCat(i32),
+    MissingData(Vec<String>),
+    UnknownRegion(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Network(err) => write!(f, "Network error: {}", err),
+            Error::Http {
+                status,
+                url,
+                message,
+            } => write!(f, "Server returned {} for {}: {}", status, url, message),
+            Error::Auth(msg) => write!(f, "Authentication failed: {}", msg),
+            Error::Parse(msg) => write!(f, "Failed to parse data: {}", msg),
+            Error::Io(err) => write!(f, "File error: {}", err),
+            Error::Verification(msg) => write!(f, "Verification failed: {}", msg),
+            Error::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            Error::MissingCat(cat_id) => {
+                write!(f, "Cat {} was not found in the banner data", cat_id)
+            }
+            Error::MissingData(files) => write!(
+                f,
+                "Missing data files: {}. Import them with `import <path> <country code>`",
+                files.join(", ")
+            ),
+            Error::UnknownRegion(cc) => {
+                write!(
+                    f,
+                    "Unknown country code {:?} (expected en, jp, kr or tw)",
+                    cc
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Error {
+        Error::Network(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Error {
+        Error::Parse(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Parse(err.to_string())
+    }
+}
+
+impl From<rustyline::error::ReadlineError> for Error {
+    fn from(err: rustyline::error::ReadlineError) -> Error {
+        match err {
+            rustyline::error::ReadlineError::Io(err) => Error::Io(err),
+            err => Error::Io(std::io::Error::other(err.to_string())),
+        }
+    }
+}
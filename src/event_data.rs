@@ -3,15 +3,30 @@ use rand::Rng;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use serde_json::json;
 use sha2::Sha256;
+use std::collections::HashMap;
 use std::time::SystemTime;
 
-async fn get_inquiry_code() -> String {
-    let url: &str = "https://nyanko-backups.ponosgames.com/?action=createAccount&referenceId=";
-    let res: reqwest::Response = reqwest::get(url).await.unwrap();
-    let body: String = res.text().await.unwrap();
-    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
-    let inquiry_code: String = json["accountId"].to_string().replace("\"", "");
-    inquiry_code
+use crate::account::{self, Account};
+use crate::config;
+use crate::error::{self, Error};
+use crate::gatya_data;
+use crate::http;
+
+fn get_json_string(json: &serde_json::Value, key: &str) -> error::Result<String> {
+    match json[key].as_str() {
+        Some(value) => Ok(value.to_string()),
+        None => Err(Error::Auth(format!("response has no {:?}: {}", key, json))),
+    }
+}
+
+async fn get_inquiry_code() -> error::Result<String> {
+    let url: String = format!(
+        "{}/?action=createAccount&referenceId=",
+        config::get().backups_url
+    );
+    let body: String = http::get_text(http::get_client().get(url)).await?;
+    let json: serde_json::Value = serde_json::from_str(&body)?;
+    get_json_string(&json, "accountId")
 }
 
 fn get_timestamp() -> i32 {
@@ -29,18 +44,93 @@ fn get_random_hex_str(length: i32) -> String {
     hex_str
 }
 
-fn generate_signature(inquiry_code: String, data: String) -> String {
-    let random_data: String = get_random_hex_str(32);
+type HmacSha256 = Hmac<Sha256>;
+
+const RANDOM_DATA_LENGTH: usize = 64;
+
+fn get_hmac(inquiry_code: &str, random_data: &str, data: &[u8]) -> HmacSha256 {
     let key: String = format!("{}{}", inquiry_code, random_data);
-    type HmacSha256 = Hmac<Sha256>;
+    // HMAC accepts keys of any length
     let mut hmac = HmacSha256::new_from_slice(key.as_bytes()).unwrap();
-    hmac.update(data.as_bytes());
+    hmac.update(data);
+    hmac
+}
+
+pub fn generate_signature(inquiry_code: String, data: String) -> String {
+    let random_data: String = get_random_hex_str(32);
+    let hmac: HmacSha256 = get_hmac(&inquiry_code, &random_data, data.as_bytes());
     let result = hmac.finalize();
     let signature = result.into_bytes();
     let signature: String = format!("{:x}", signature);
     format!("{}{}", random_data, signature)
 }
 
+// Signatures are the random data used in the key followed by the hex HMAC of the data
+pub fn verify_signature(inquiry_code: &str, data: &[u8], signature: &str) -> bool {
+    if signature.len() <= RANDOM_DATA_LENGTH || !signature.is_char_boundary(RANDOM_DATA_LENGTH) {
+        return false;
+    }
+    let (random_data, expected) = signature.split_at(RANDOM_DATA_LENGTH);
+    let expected: Vec<u8> = match hex::decode(expected) {
+        Ok(expected) => expected,
+        Err(_) => return false,
+    };
+    get_hmac(inquiry_code, random_data, data)
+        .verify_slice(&expected)
+        .is_ok()
+}
+
+// The auth and event servers sign every response, so one without a valid signature is rejected.
+// The signing key is only known from how requests are signed, so this can be turned down to a
+// warning with --skip-signature-check or `verify_signatures` if the servers ever sign differently
+fn verify_response(
+    headers: &HeaderMap,
+    body: Vec<u8>,
+    inquiry_code: &str,
+    strict: bool,
+) -> error::Result<String> {
+    let problem: Option<&str> = match headers.get("nyanko-signature") {
+        None => Some("the response has no nyanko-signature header"),
+        Some(signature) => {
+            let signature: &str = signature.to_str().unwrap_or("");
+            if verify_signature(inquiry_code, &body, signature) {
+                None
+            } else {
+                Some("the nyanko-signature header does not match the response")
+            }
+        }
+    };
+    match problem {
+        Some(problem) if strict => return Err(Error::Verification(problem.to_string())),
+        Some(problem) => eprintln!("Warning: {}, using it anyway", problem),
+        None => {}
+    }
+    String::from_utf8(body)
+        .map_err(|_| Error::Verification("the response is not valid UTF-8".to_string()))
+}
+
+async fn get_verified_text(
+    request: reqwest::RequestBuilder,
+    inquiry_code: &str,
+) -> error::Result<String> {
+    let (headers, body) = http::get_response(request).await?;
+    verify_response(
+        &headers,
+        body,
+        inquiry_code,
+        config::get().verify_signatures,
+    )
+}
+
+// gatya.tsv is wrapped in [start] and [end] lines, so a missing [end] means it was cut off
+fn check_event_data_complete(data: &str) -> error::Result<()> {
+    let data: &str = data.trim();
+    if data.starts_with("[start]") && !data.ends_with("[end]") {
+        return Err(Error::Verification("gatya.tsv is truncated".to_string()));
+    }
+    Ok(())
+}
+
 fn get_headers(inquiry_code: String, data: String) -> HeaderMap {
     let mut headers = HeaderMap::new();
     let signature: String = generate_signature(inquiry_code, data);
@@ -54,61 +144,102 @@ fn get_headers(inquiry_code: String, data: String) -> HeaderMap {
     );
     headers.insert(
         HeaderName::from_static("nyanko-signature-version"),
-        HeaderValue::from_str("1").unwrap(),
+        HeaderValue::from_static("1"),
     );
     headers.insert(
         HeaderName::from_static("nyanko-signature-algorithm"),
-        HeaderValue::from_str("HMACSHA256").unwrap(),
+        HeaderValue::from_static("HMACSHA256"),
     );
     headers.insert(
         HeaderName::from_static("accept-enconding"),
-        HeaderValue::from_str("gzip").unwrap(),
+        HeaderValue::from_static("gzip"),
     );
     headers.insert(
         HeaderName::from_static("connection"),
-        HeaderValue::from_str("keep-alive").unwrap(),
+        HeaderValue::from_static("keep-alive"),
     );
     headers.insert(
         HeaderName::from_static("user-agent"),
-        HeaderValue::from_str("Dalvik/2.1.0 (Linux; U; Android 9; Pixel 2 Build/PQ3A.190801.002)")
-            .unwrap(),
+        HeaderValue::from_static(
+            "Dalvik/2.1.0 (Linux; U; Android 9; Pixel 2 Build/PQ3A.190801.002)",
+        ),
     );
     headers.insert(
         HeaderName::from_static("content-type"),
-        HeaderValue::from_str("application/json").unwrap(),
+        HeaderValue::from_static("application/json"),
     );
     headers
 }
 
-async fn get_password(inquiry_code: String) -> String {
-    let url: &str = "https://nyanko-auth.ponosgames.com/v1/users";
+async fn get_password(inquiry_code: String) -> error::Result<String> {
+    let url: String = format!("{}/v1/users", config::get().auth_url);
     let json = json!({
         "accountCode": inquiry_code,
         "accountCreatedAt": get_timestamp().to_string(),
         "nonce": get_random_hex_str(16),
     });
-    let client: reqwest::Client = reqwest::Client::new();
-    let headers = get_headers(inquiry_code, json.to_string());
-    let res = client
+    let headers = get_headers(inquiry_code.clone(), json.to_string());
+    let request: reqwest::RequestBuilder = http::get_client()
         .post(url)
         .body(json.to_string())
-        .headers(headers)
-        .send()
-        .await
-        .unwrap();
-    let body = res.text().await.unwrap();
-    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        .headers(headers);
+    let body: String = get_verified_text(request, &inquiry_code).await?;
+    let json: serde_json::Value = serde_json::from_str(&body)?;
     let payload: serde_json::Value = json["payload"].to_owned();
-    let password: String = payload["password"].to_string().replace("\"", "");
-    password
+    get_json_string(&payload, "password")
+}
+
+// Used when the game version can't be looked up
+const DEFAULT_CLIENT_VERSION: &str = "120200";
+
+// Turns a BCData version like "12.2.0en" into the "120200" format the server expects
+fn get_client_version_from_game_version(game_version: &str) -> Option<String> {
+    let version: &str = game_version.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let parts: Vec<u32> = version
+        .split('.')
+        .map(|part| part.parse::<u32>())
+        .collect::<Result<Vec<u32>, _>>()
+        .ok()?;
+    match parts.as_slice() {
+        [major, minor, patch] if *minor < 100 && *patch < 100 => {
+            Some(format!("{}{:02}{:02}", major, minor, patch))
+        }
+        _ => None,
+    }
+}
+
+async fn get_client_version(cc: &str) -> String {
+    if let Some(client_version) = &config::get().client_version {
+        return client_version.clone();
+    }
+    let game_version: String = match gatya_data::get_latest_game_data_version(cc).await {
+        Ok(game_version) => game_version,
+        Err(err) => {
+            eprintln!(
+                "Could not get the game version, using {}. {}",
+                DEFAULT_CLIENT_VERSION, err
+            );
+            return DEFAULT_CLIENT_VERSION.to_string();
+        }
+    };
+    match get_client_version_from_game_version(&game_version) {
+        Some(client_version) => client_version,
+        None => {
+            eprintln!(
+                "Unknown game version {:?}, using {}",
+                game_version, DEFAULT_CLIENT_VERSION
+            );
+            DEFAULT_CLIENT_VERSION.to_string()
+        }
+    }
 }
 
-fn get_client_info(cc: &str) -> serde_json::Value {
+fn get_client_info(cc: &str, client_version: &str) -> serde_json::Value {
     let data: serde_json::Value = json!({
         "clientInfo": {
             "client": {
                 "countryCode": cc.replace("jp", "ja"),
-                "version": "120200",
+                "version": client_version,
             },
             "device": {
                 "model": "SM-G955F"
@@ -123,41 +254,205 @@ fn get_client_info(cc: &str) -> serde_json::Value {
     data
 }
 
-async fn get_token(cc: &str) -> String {
-    let inquiry_code: String = get_inquiry_code().await;
-    let password: String = get_password(inquiry_code.clone()).await;
-    let mut client_info: serde_json::Value = get_client_info(cc);
+async fn create_account() -> error::Result<Account> {
+    let inquiry_code: String = get_inquiry_code().await?;
+    let password: String = get_password(inquiry_code.clone()).await?;
+    let account: Account = Account {
+        inquiry_code,
+        password,
+        tokens: HashMap::new(),
+    };
+    account::save_account(&account)?;
+    Ok(account)
+}
+
+async fn request_token(cc: &str, account: &Account) -> error::Result<String> {
+    let client_version: String = get_client_version(cc).await;
+    let mut client_info: serde_json::Value = get_client_info(cc, &client_version);
 
-    let url: &str = "https://nyanko-auth.ponosgames.com/v1/tokens";
-    client_info["password"] = serde_json::Value::String(password);
-    client_info["accountCode"] = serde_json::Value::String(inquiry_code.clone());
-    let headers = get_headers(inquiry_code, client_info.to_string());
+    let url: String = format!("{}/v1/tokens", config::get().auth_url);
+    client_info["password"] = serde_json::Value::String(account.password.clone());
+    client_info["accountCode"] = serde_json::Value::String(account.inquiry_code.clone());
+    let headers = get_headers(account.inquiry_code.clone(), client_info.to_string());
 
-    let client = reqwest::Client::new();
-    let res = client
+    let request: reqwest::RequestBuilder = http::get_client()
         .post(url)
         .body(client_info.to_string())
-        .headers(headers)
-        .send()
-        .await
-        .unwrap();
-    let body = res.text().await.unwrap();
-    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        .headers(headers);
+    let body: String = get_verified_text(request, &account.inquiry_code).await?;
+    let json: serde_json::Value = serde_json::from_str(&body)?;
     let payload: serde_json::Value = json["payload"].to_owned();
-    let token: String = payload["token"].to_string().replace("\"", "");
-    token
+    get_json_string(&payload, "token")
+}
+
+// Reuses the saved account and token, only creating a new account if the saved one stops working
+async fn get_token(cc: &str) -> error::Result<(Account, String)> {
+    let now: i64 = get_timestamp() as i64;
+    let mut account: Account = match account::load_account() {
+        Some(account) => {
+            if let Some(token) = account.get_valid_token(cc, now) {
+                return Ok((account, token));
+            }
+            account
+        }
+        None => create_account().await?,
+    };
+
+    let token: String = match request_token(cc, &account).await {
+        Ok(token) => token,
+        Err(Error::Auth(_))
+        | Err(Error::Http {
+            status: 400..=499, ..
+        }) => {
+            eprintln!("Saved account was rejected, creating a new one...");
+            account = create_account().await?;
+            request_token(cc, &account).await?
+        }
+        Err(err) => return Err(err),
+    };
+    account.tokens.insert(cc.to_string(), token.clone());
+    account::save_account(&account)?;
+    Ok((account, token))
+}
+
+fn forget_token(cc: &str, mut account: Account) -> error::Result<()> {
+    account.tokens.remove(cc);
+    account::save_account(&account)
 }
 
-pub async fn get_event_data(cc: &str) -> String {
-    let token: String = get_token(cc).await;
+async fn request_event_data(cc: &str, account: &Account, token: &str) -> error::Result<String> {
     let cc_code: String = cc.replace("jp", "");
     let base_url: String = format!(
-        "https://nyanko-events.ponosgames.com/battlecats{}_production/gatya.tsv",
+        "{}/battlecats{}_production/gatya.tsv",
+        config::get().events_url,
         cc_code,
     );
     let url: String = format!("{}?jwt={}", base_url, token);
-    let client: reqwest::Client = reqwest::Client::new();
-    let res: reqwest::Response = client.get(url).send().await.unwrap();
-    let body: String = res.text().await.unwrap();
-    body
+    let data: String =
+        get_verified_text(http::get_client().get(url), &account.inquiry_code).await?;
+    check_event_data_complete(&data)?;
+    Ok(data)
+}
+
+pub async fn get_event_data(cc: &str) -> error::Result<String> {
+    let (account, token) = get_token(cc).await?;
+    match request_event_data(cc, &account, &token).await {
+        Err(Error::Http {
+            status: 401 | 403, ..
+        }) => {
+            // the server can revoke a token before it expires
+            forget_token(cc, account)?;
+            let (account, token) = get_token(cc).await?;
+            request_event_data(cc, &account, &token).await
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INQUIRY_CODE: &str = "abc123";
+    const BODY: &[u8] = b"[start]\nMock Banner\n[end]\n";
+    // Worked out separately with Python's hmac module:
+    // hmac.new((code + random).encode(), body, hashlib.sha256).hexdigest()
+    // This assumes the servers key the HMAC with the inquiry code followed by the random data,
+    // the same way requests are signed
+    const KNOWN_SIGNATURE: &str =
+        "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef\
+        81fbd62aa0c4f7d3de8399116b1d6e53fa8e72a10cd59a176e6cb57fcb0164ee";
+
+    fn get_signed_headers(signature: &str) -> HeaderMap {
+        let mut headers: HeaderMap = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("nyanko-signature"),
+            HeaderValue::from_str(signature).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn matches_a_known_signature() {
+        assert!(verify_signature(INQUIRY_CODE, BODY, KNOWN_SIGNATURE));
+        assert!(!verify_signature("other", BODY, KNOWN_SIGNATURE));
+    }
+
+    #[test]
+    fn verifies_generated_signatures() {
+        let signature: String = generate_signature(
+            INQUIRY_CODE.to_string(),
+            String::from_utf8(BODY.to_vec()).unwrap(),
+        );
+        assert_eq!(signature.len(), RANDOM_DATA_LENGTH + 64);
+        assert!(verify_signature(INQUIRY_CODE, BODY, &signature));
+    }
+
+    #[test]
+    fn accepts_a_signed_response() {
+        let headers: HeaderMap = get_signed_headers(KNOWN_SIGNATURE);
+        let data: String = verify_response(&headers, BODY.to_vec(), INQUIRY_CODE, true).unwrap();
+        assert_eq!(data.as_bytes(), BODY);
+    }
+
+    #[test]
+    fn rejects_a_tampered_response() {
+        let headers: HeaderMap = get_signed_headers(KNOWN_SIGNATURE);
+        let body: Vec<u8> = b"[start]\nFake Banner\n[end]\n".to_vec();
+        assert!(matches!(
+            verify_response(&headers, body, INQUIRY_CODE, true),
+            Err(Error::Verification(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_a_truncated_response() {
+        let headers: HeaderMap = get_signed_headers(KNOWN_SIGNATURE);
+        let body: Vec<u8> = BODY[..BODY.len() - 6].to_vec();
+        assert!(matches!(
+            verify_response(&headers, body, INQUIRY_CODE, true),
+            Err(Error::Verification(_))
+        ));
+        assert!(check_event_data_complete("[start]\nMock Banner\n").is_err());
+        assert!(check_event_data_complete("[start]\nMock Banner\n[end]\n").is_ok());
+    }
+
+    #[test]
+    fn rejects_an_unsigned_response() {
+        assert!(matches!(
+            verify_response(&HeaderMap::new(), BODY.to_vec(), INQUIRY_CODE, true),
+            Err(Error::Verification(_))
+        ));
+        let headers: HeaderMap = get_signed_headers("tooshort");
+        assert!(matches!(
+            verify_response(&headers, BODY.to_vec(), INQUIRY_CODE, true),
+            Err(Error::Verification(_))
+        ));
+    }
+
+    #[test]
+    fn only_warns_about_signatures_when_not_strict() {
+        let headers: HeaderMap = get_signed_headers(KNOWN_SIGNATURE);
+        let body: Vec<u8> = b"[start]\nFake Banner\n[end]\n".to_vec();
+        let data: String = verify_response(&headers, body, INQUIRY_CODE, false).unwrap();
+        assert_eq!(data, "[start]\nFake Banner\n[end]\n");
+        let data: String =
+            verify_response(&HeaderMap::new(), BODY.to_vec(), INQUIRY_CODE, false).unwrap();
+        assert_eq!(data.as_bytes(), BODY);
+        // a body that isn't text is still rejected
+        assert!(verify_response(&HeaderMap::new(), vec![0xff], INQUIRY_CODE, false).is_err());
+    }
+
+    #[test]
+    fn converts_game_versions() {
+        assert_eq!(
+            get_client_version_from_game_version("13.1.0en").as_deref(),
+            Some("130100")
+        );
+        assert_eq!(
+            get_client_version_from_game_version("9.10.2").as_deref(),
+            Some("91002")
+        );
+        assert_eq!(get_client_version_from_game_version("13.1"), None);
+    }
 }
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use aes::cipher::block_padding::NoPadding;
+use aes::cipher::{BlockDecryptMut, KeyInit, KeyIvInit};
+use md5::{Digest, Md5};
+
+use crate::error::{self, Error};
+
+type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+type Aes128EcbDec = ecb::Decryptor<aes::Aes128>;
+
+const BLOCK_SIZE: usize = 16;
+
+struct PackEntry {
+    name: String,
+    offset: usize,
+    size: usize,
+}
+
+// DataLocal packs are encrypted with a different key and iv for each region
+fn get_pack_key(cc: &str) -> Option<(&'static str, &'static str)> {
+    match cc {
+        "en" => Some((
+            "0ad39e4aeaf55aa717feb1825edef521",
+            "d1d7e708091941d90cdf8aa5f30bb0c2",
+        )),
+        "jp" => Some((
+            "d754868de89d717fa9e7b06da45ae9e3",
+            "40b2131a9f388ad4e5002a98118f6128",
+        )),
+        "kr" => Some((
+            "bea585eb993216ef4dcb88b625c3df98",
+            "9b13c2121d39f1353a125fed98696649",
+        )),
+        "tw" => Some((
+            "313d9858a7fb939def1d7d859629087d",
+            "0e3743eb53bf5944d1ae7e10c2e54bdf",
+        )),
+        _ => None,
+    }
+}
+
+// List files use the first half of md5("pack") as an ASCII key
+fn get_list_key() -> Vec<u8> {
+    let hash: String = format!("{:x}", Md5::digest(b"pack"));
+    hash.as_bytes()[..BLOCK_SIZE].to_vec()
+}
+
+// Removes PKCS#7 padding, returning false if the data doesn't end with any
+fn strip_padding(data: &mut Vec<u8>) -> bool {
+    let padding: usize = match data.last() {
+        Some(last) => *last as usize,
+        None => return false,
+    };
+    if padding == 0 || padding > BLOCK_SIZE || padding > data.len() {
+        return false;
+    }
+    if !data[data.len() - padding..]
+        .iter()
+        .all(|byte| *byte as usize == padding)
+    {
+        return false;
+    }
+    data.truncate(data.len() - padding);
+    true
+}
+
+fn decrypt_list(data: &[u8]) -> Result<String, String> {
+    if data.is_empty() || !data.len().is_multiple_of(BLOCK_SIZE) {
+        return Err(format!(
+            "list file size {} is not a multiple of {}",
+            data.len(),
+            BLOCK_SIZE
+        ));
+    }
+    let key: Vec<u8> = get_list_key();
+    let mut buffer: Vec<u8> = data.to_vec();
+    Aes128EcbDec::new(key.as_slice().into())
+        .decrypt_padded_mut::<NoPadding>(&mut buffer)
+        .map_err(|err| format!("failed to decrypt list file: {}", err))?;
+    strip_padding(&mut buffer);
+    String::from_utf8(buffer).map_err(|_| "list file did not decrypt to text".to_string())
+}
+
+// The first line is the number of files, then each line is name,offset,size
+fn parse_list(data: &str) -> Result<Vec<PackEntry>, String> {
+    let mut entries: Vec<PackEntry> = Vec::new();
+    for line in data.lines().skip(1) {
+        let line: &str = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let parts: Vec<&str> = line.split(',').collect();
+        if parts.len() < 3 {
+            return Err(format!("bad list entry {:?}", line));
+        }
+        let offset: usize = parts[1]
+            .trim()
+            .parse()
+            .map_err(|_| format!("bad offset in list entry {:?}", line))?;
+        let size: usize = parts[2]
+            .trim()
+            .parse()
+            .map_err(|_| format!("bad size in list entry {:?}", line))?;
+        entries.push(PackEntry {
+            name: parts[0].trim().to_string(),
+            offset,
+            size,
+        });
+    }
+    Ok(entries)
+}
+
+fn decrypt_pack_file(name: &str, data: &[u8], cc: &str) -> Result<Vec<u8>, String> {
+    if data.is_empty() || !data.len().is_multiple_of(BLOCK_SIZE) {
+        return Err(format!(
+            "{} is {} bytes, which is not a multiple of {}, so the pack is damaged",
+            name,
+            data.len(),
+            BLOCK_SIZE
+        ));
+    }
+    let (key, iv) = match get_pack_key(cc) {
+        Some(key) => key,
+        None => return Err(format!("no pack key for region {:?}", cc)),
+    };
+    let key: Vec<u8> = hex::decode(key).unwrap();
+    let iv: Vec<u8> = hex::decode(iv).unwrap();
+    let mut buffer: Vec<u8> = data.to_vec();
+    Aes128CbcDec::new(key.as_slice().into(), iv.as_slice().into())
+        .decrypt_padded_mut::<NoPadding>(&mut buffer)
+        .map_err(|err| format!("failed to decrypt {}: {}", name, err))?;
+    // a wrong key still decrypts, but to noise without valid padding
+    if !strip_padding(&mut buffer) {
+        return Err(format!(
+            "{} did not decrypt with the {} key, check the pack is from that region",
+            name, cc
+        ));
+    }
+    Ok(buffer)
+}
+
+// Decrypts the requested files out of a .list/.pack pair, skipping any the pack doesn't have
+pub fn extract_files(
+    list_data: &[u8],
+    pack_data: &[u8],
+    cc: &str,
+    file_names: &[&str],
+) -> error::Result<HashMap<String, Vec<u8>>> {
+    let list: String = decrypt_list(list_data).map_err(Error::Parse)?;
+    let entries: Vec<PackEntry> = parse_list(&list).map_err(Error::Parse)?;
+
+    let mut files: HashMap<String, Vec<u8>> = HashMap::new();
+    for entry in entries {
+        if !file_names.contains(&entry.name.as_str()) {
+            continue;
+        }
+        let end: usize = match entry.offset.checked_add(entry.size) {
+            Some(end) if end <= pack_data.len() => end,
+            _ => {
+                return Err(Error::Parse(format!(
+                    "{} runs past the end of the pack (offset {}, size {}, pack {} bytes)",
+                    entry.name,
+                    entry.offset,
+                    entry.size,
+                    pack_data.len()
+                )))
+            }
+        };
+        let data: Vec<u8> = decrypt_pack_file(&entry.name, &pack_data[entry.offset..end], cc)
+            .map_err(Error::Parse)?;
+        files.insert(entry.name, data);
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use aes::cipher::block_padding::Pkcs7;
+    use aes::cipher::BlockEncryptMut;
+
+    use super::*;
+
+    type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+    type Aes128EcbEnc = ecb::Encryptor<aes::Aes128>;
+
+    fn pad_buffer(data: &[u8]) -> Vec<u8> {
+        let mut buffer: Vec<u8> = data.to_vec();
+        buffer.resize(data.len() + BLOCK_SIZE, 0);
+        buffer
+    }
+
+    fn encrypt_list(data: &str) -> Vec<u8> {
+        let key: Vec<u8> = get_list_key();
+        let mut buffer: Vec<u8> = pad_buffer(data.as_bytes());
+        let len: usize = Aes128EcbEnc::new(key.as_slice().into())
+            .encrypt_padded_mut::<Pkcs7>(&mut buffer, data.len())
+            .unwrap()
+            .len();
+        buffer.truncate(len);
+        buffer
+    }
+
+    fn encrypt_pack_file(data: &[u8], cc: &str) -> Vec<u8> {
+        let (key, iv) = get_pack_key(cc).unwrap();
+        let key: Vec<u8> = hex::decode(key).unwrap();
+        let iv: Vec<u8> = hex::decode(iv).unwrap();
+        let mut buffer: Vec<u8> = pad_buffer(data);
+        let len: usize = Aes128CbcEnc::new(key.as_slice().into(), iv.as_slice().into())
+            .encrypt_padded_mut::<Pkcs7>(&mut buffer, data.len())
+            .unwrap()
+            .len();
+        buffer.truncate(len);
+        buffer
+    }
+
+    // Builds a .list/.pack pair holding the given files, encrypted for a region
+    fn build_pack(files: &[(&str, &[u8])], cc: &str) -> (Vec<u8>, Vec<u8>) {
+        let mut list: String = format!("{}\n", files.len());
+        let mut pack: Vec<u8> = Vec::new();
+        for (name, data) in files {
+            let encrypted: Vec<u8> = encrypt_pack_file(data, cc);
+            list.push_str(&format!("{},{},{}\n", name, pack.len(), encrypted.len()));
+            pack.extend_from_slice(&encrypted);
+        }
+        (encrypt_list(&list), pack)
+    }
+
+    #[test]
+    fn extracts_requested_files() {
+        let (list, pack) = build_pack(
+            &[
+                ("unitbuy.csv", b"0,0,0\n1,1,1\n"),
+                ("GatyaDataSetR1.csv", b"0,1,2,-1\n"),
+                ("other.csv", b"not wanted"),
+            ],
+            "en",
+        );
+        let files: HashMap<String, Vec<u8>> =
+            extract_files(&list, &pack, "en", &["unitbuy.csv", "GatyaDataSetR1.csv"]).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files["unitbuy.csv"], b"0,0,0\n1,1,1\n");
+        assert_eq!(files["GatyaDataSetR1.csv"], b"0,1,2,-1\n");
+    }
+
+    #[test]
+    fn rejects_the_wrong_region() {
+        let (list, pack) = build_pack(&[("unitbuy.csv", b"0,0,0\n1,1,1\n")], "jp");
+        match extract_files(&list, &pack, "en", &["unitbuy.csv"]) {
+            Err(Error::Parse(message)) => assert!(message.contains("region"), "{}", message),
+            _ => panic!("expected a wrong region error"),
+        }
+    }
+
+    #[test]
+    fn rejects_entries_past_the_end() {
+        let list: Vec<u8> = encrypt_list("1\nunitbuy.csv,16,32\n");
+        assert!(matches!(
+            extract_files(&list, &[0; 32], "en", &["unitbuy.csv"]),
+            Err(Error::Parse(_))
+        ));
+        let list: Vec<u8> = encrypt_list(&format!("1\nunitbuy.csv,{},16\n", usize::MAX));
+        assert!(matches!(
+            extract_files(&list, &[0; 32], "en", &["unitbuy.csv"]),
+            Err(Error::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_unaligned_files() {
+        let list: Vec<u8> = encrypt_list("1\nunitbuy.csv,0,10\n");
+        assert!(matches!(
+            extract_files(&list, &[0; 16], "en", &["unitbuy.csv"]),
+            Err(Error::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_list_files() {
+        assert!(decrypt_list(&[0; 15]).is_err());
+        assert!(parse_list("1\nunitbuy.csv,0\n").is_err());
+        assert!(parse_list("1\nunitbuy.csv,x,16\n").is_err());
+    }
+}
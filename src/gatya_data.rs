@@ -1,6 +1,15 @@
+use bitflags::bitflags;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use std::path::PathBuf;
+
+use crate::cache;
+use crate::config;
+use crate::error::{self, Error};
+use crate::http;
 use crate::{BLANK_SLOT, BLANK_SLOT_USER};
 
-fn parse_csv(data: String, delimiter: char) -> Vec<Vec<String>> {
+fn parse_csv(data: String, delimiter: char) -> error::Result<Vec<Vec<String>>> {
     let mut reader = csv::ReaderBuilder::new()
         .delimiter(delimiter as u8)
         .has_headers(false)
@@ -8,124 +17,426 @@ fn parse_csv(data: String, delimiter: char) -> Vec<Vec<String>> {
         .from_reader(data.as_bytes());
     let mut records: Vec<Vec<String>> = Vec::new();
     for result in reader.records() {
-        let record = result.unwrap();
+        let record = result?;
         let mut row: Vec<String> = Vec::new();
         for field in record.iter() {
             row.push(field.to_string());
         }
         records.push(row);
     }
-    records
+    Ok(records)
+}
+
+bitflags! {
+    // Only step-up (4) is known for sure, the other bits are kept as-is but not shown
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct GatyaFlags: u32 {
+        const STEP_UP = 1 << 2;
+    }
+}
+
+// Column 9 of gatya.tsv, each type has its own GatyaDataSet file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GatyaType {
+    Normal,
+    Rare,
+    Event,
+    Other(u32),
+}
+
+impl GatyaType {
+    fn from_code(code: u32) -> GatyaType {
+        match code {
+            0 => GatyaType::Normal,
+            1 => GatyaType::Rare,
+            4 => GatyaType::Event,
+            _ => GatyaType::Other(code),
+        }
+    }
+
+    pub fn get_dataset_name(&self) -> Option<&'static str> {
+        match self {
+            GatyaType::Normal => Some("GatyaDataSetN1.csv"),
+            GatyaType::Rare => Some("GatyaDataSetR1.csv"),
+            GatyaType::Event => Some("GatyaDataSetE1.csv"),
+            GatyaType::Other(_) => None,
+        }
+    }
+
+    // The rare dataset keeps its original cache name so existing caches and imports still work
+    pub fn get_local_name(&self, cc: &str) -> Option<String> {
+        match self {
+            GatyaType::Normal => Some(format!("gatya_n1_{}.csv", cc)),
+            GatyaType::Rare => Some(format!("gatya_{}.csv", cc)),
+            GatyaType::Event => Some(format!("gatya_e1_{}.csv", cc)),
+            GatyaType::Other(_) => None,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct GatyaEvent {
-    pub index: u32,
-    pub start: String,
-    pub end: String,
-    pub gatya_id: String,
-    pub rare_chance: String,
-    pub super_rare_chance: String,
-    pub uber_rare_chance: String,
-    pub legend_rare_chance: String,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub gatya_type: GatyaType,
+    pub gatya_id: u32,
+    pub rare_chance: u32,
+    pub super_rare_chance: u32,
+    pub uber_rare_chance: u32,
+    pub legend_rare_chance: u32,
+    pub price: i32,
+    pub flags: GatyaFlags,
+    pub guaranteed: bool,
     pub banner_txt: String,
 }
 
-fn parse_gatya_event(index: i32, line: Vec<String>) -> GatyaEvent {
-    let length: i32 = line[9].parse::<i32>().unwrap();
-    let gatya_id_pos: i32 = 10 + (15 * (length - 1));
-    let rare_chance_pos: i32 = 16 + (15 * (length - 1));
-    let super_rare_chance_pos: i32 = 18 + (15 * (length - 1));
-    let uber_rare_chance_pos: i32 = 20 + (15 * (length - 1));
-    let legend_rare_chance_pos: i32 = 22 + (15 * (length - 1));
-    let banner_text_pos: i32 = 24 + (15 * (length - 1));
+impl GatyaEvent {
+    // Cut-off points for `seed % 10000`, returned as (super rare, uber rare, legend rare)
+    pub fn get_rarity_thresholds(&self) -> (u32, u32, u32) {
+        let legend_chance: u32 = 10000 - self.legend_rare_chance;
+        let uber_chance: u32 = legend_chance - self.uber_rare_chance;
+        let super_rare_chance: u32 = uber_chance - self.super_rare_chance;
+        (super_rare_chance, uber_chance, legend_chance)
+    }
 
-    GatyaEvent {
-        index: index as u32,
-        start: line[0].to_string(),
-        end: line[2].to_string(),
-        gatya_id: line[gatya_id_pos as usize].to_string(),
-        rare_chance: line[rare_chance_pos as usize].to_string(),
-        super_rare_chance: line[super_rare_chance_pos as usize].to_string(),
-        uber_rare_chance: line[uber_rare_chance_pos as usize].to_string(),
-        legend_rare_chance: line[legend_rare_chance_pos as usize].to_string(),
-        banner_txt: line[banner_text_pos as usize].to_string(),
+    pub fn get_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        match self.gatya_type {
+            GatyaType::Rare => {}
+            GatyaType::Normal => tags.push("normal capsule".to_string()),
+            GatyaType::Event => tags.push("event capsule".to_string()),
+            GatyaType::Other(code) => tags.push(format!("gatya type {}", code)),
+        }
+        if self.guaranteed {
+            tags.push("guaranteed".to_string());
+        }
+        if self.flags.contains(GatyaFlags::STEP_UP) {
+            tags.push("step-up".to_string());
+        }
+        tags
     }
 }
 
-pub fn parse_gatya_events(data: String) -> Vec<GatyaEvent> {
-    let records: Vec<Vec<String>> = parse_csv(data, '\t');
+fn get_field<'a>(line: &'a [String], pos: usize, name: &str) -> Result<&'a str, String> {
+    match line.get(pos) {
+        Some(field) => Ok(field.trim()),
+        None => Err(format!("missing {} (column {})", name, pos + 1)),
+    }
+}
+
+fn parse_field<T: std::str::FromStr>(line: &[String], pos: usize, name: &str) -> Result<T, String> {
+    let field: &str = get_field(line, pos, name)?;
+    field
+        .parse::<T>()
+        .map_err(|_| format!("invalid {} {:?} (column {})", name, field, pos + 1))
+}
+
+fn parse_date_time(line: &[String], date_pos: usize, name: &str) -> Result<NaiveDateTime, String> {
+    let date_str: &str = get_field(line, date_pos, name)?;
+    let date: NaiveDate = NaiveDate::parse_from_str(date_str, "%Y%m%d")
+        .map_err(|_| format!("invalid {} date {:?}", name, date_str))?;
+
+    // times are stored as HHMM without leading zeros, e.g. "1100" or "0"
+    let time_value: u32 = parse_field(line, date_pos + 1, name)?;
+    let time: NaiveTime = NaiveTime::from_hms_opt(time_value / 100, time_value % 100, 0)
+        .ok_or_else(|| format!("invalid {} time {}", name, time_value))?;
+    Ok(date.and_time(time))
+}
+
+fn parse_gatya_event(
+    line: &[String],
+    entry: usize,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    gatya_type: GatyaType,
+) -> Result<GatyaEvent, String> {
+    let offset: usize = 15 * entry;
+    let gatya_id_pos: usize = 10 + offset;
+    let price_pos: usize = 11 + offset;
+    let flags_pos: usize = 13 + offset;
+    let rare_chance_pos: usize = 16 + offset;
+    let super_rare_chance_pos: usize = 18 + offset;
+    let uber_rare_chance_pos: usize = 20 + offset;
+    let guaranteed_pos: usize = 21 + offset;
+    let legend_rare_chance_pos: usize = 22 + offset;
+    let banner_text_pos: usize = 24 + offset;
+
+    let gatya_event: GatyaEvent = GatyaEvent {
+        start,
+        end,
+        gatya_type,
+        gatya_id: parse_field(line, gatya_id_pos, "gatya id")?,
+        rare_chance: parse_field(line, rare_chance_pos, "rare chance")?,
+        super_rare_chance: parse_field(line, super_rare_chance_pos, "super rare chance")?,
+        uber_rare_chance: parse_field(line, uber_rare_chance_pos, "uber rare chance")?,
+        legend_rare_chance: parse_field(line, legend_rare_chance_pos, "legend rare chance")?,
+        price: parse_field(line, price_pos, "price")?,
+        flags: GatyaFlags::from_bits_retain(parse_field(line, flags_pos, "flags")?),
+        guaranteed: parse_field::<u32>(line, guaranteed_pos, "guaranteed flag")? != 0,
+        banner_txt: get_field(line, banner_text_pos, "banner text")?.to_string(),
+    };
+
+    let total_chance: u32 = gatya_event.rare_chance
+        + gatya_event.super_rare_chance
+        + gatya_event.uber_rare_chance
+        + gatya_event.legend_rare_chance;
+    if total_chance > 10000 {
+        return Err(format!(
+            "rarity chances add up to {} (max 10000)",
+            total_chance
+        ));
+    }
+    Ok(gatya_event)
+}
+
+// Returns the entries that parsed and an error for each one that didn't,
+// only failing as a whole when the fields shared by every entry are bad
+fn parse_gatya_line(line: Vec<String>) -> Result<(Vec<GatyaEvent>, Vec<String>), String> {
+    let start: NaiveDateTime = parse_date_time(&line, 0, "start")?;
+    let end: NaiveDateTime = parse_date_time(&line, 2, "end")?;
+    let gatya_type: GatyaType = GatyaType::from_code(parse_field(&line, 8, "gatya type")?);
+    let length: usize = parse_field(&line, 9, "gatya count")?;
+
+    let mut gatya_events: Vec<GatyaEvent> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+    for entry in 0..length {
+        match parse_gatya_event(&line, entry, start, end, gatya_type) {
+            Ok(gatya_event) => gatya_events.push(gatya_event),
+            Err(err) => errors.push(format!("entry {}: {}", entry + 1, err)),
+        }
+    }
+    Ok((gatya_events, errors))
+}
+
+pub fn parse_gatya_events(data: String) -> error::Result<Vec<GatyaEvent>> {
+    let records: Vec<Vec<String>> = parse_csv(data, '\t')?;
     let mut gatya_events: Vec<GatyaEvent> = Vec::new();
     for (index, record) in records.iter().enumerate() {
         if record.len() < 25 {
             continue;
         }
-        gatya_events.push(parse_gatya_event(index as i32, record.to_vec()));
+        // one bad line shouldn't hide every other event
+        match parse_gatya_line(record.to_vec()) {
+            Ok((mut line_events, errors)) => {
+                gatya_events.append(&mut line_events);
+                for err in errors {
+                    eprintln!(
+                        "Skipping malformed gatya event on line {}: {}",
+                        index + 1,
+                        err
+                    );
+                }
+            }
+            Err(err) => eprintln!(
+                "Skipping malformed gatya event on line {}: {}",
+                index + 1,
+                err
+            ),
+        }
     }
-    gatya_events
+    Ok(gatya_events)
 }
 
-pub fn get_gatya_event(data: &[GatyaEvent], gatya_id: i32) -> GatyaEvent {
-    for gatya_event in data.iter() {
-        if gatya_event.gatya_id.parse::<i32>().unwrap() == gatya_id {
-            return gatya_event.clone();
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum EventStatus {
+    Running,
+    Upcoming,
+    Past,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum EventSort {
+    #[default]
+    File,
+    Start,
+    End,
+    GatyaId,
+}
+
+#[derive(Clone, Default)]
+pub struct EventFilter {
+    pub status: Option<EventStatus>,
+    pub text: Option<String>,
+    pub gatya_id: Option<u32>,
+    pub sort: EventSort,
+}
+
+impl GatyaEvent {
+    pub fn get_status(&self, now: NaiveDateTime) -> EventStatus {
+        if now < self.start {
+            EventStatus::Upcoming
+        } else if now >= self.end {
+            EventStatus::Past
+        } else {
+            EventStatus::Running
         }
     }
-    panic!("Gatya event not found");
+
+    pub fn matches(&self, filter: &EventFilter, now: NaiveDateTime) -> bool {
+        if let Some(status) = filter.status {
+            if self.get_status(now) != status {
+                return false;
+            }
+        }
+        if let Some(text) = &filter.text {
+            if !self
+                .banner_txt
+                .to_lowercase()
+                .contains(&text.to_lowercase())
+            {
+                return false;
+            }
+        }
+        if let Some(gatya_id) = filter.gatya_id {
+            if self.gatya_id != gatya_id {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+pub fn filter_events(
+    gatya_events: Vec<GatyaEvent>,
+    filter: &EventFilter,
+    now: NaiveDateTime,
+) -> Vec<GatyaEvent> {
+    let mut gatya_events: Vec<GatyaEvent> = gatya_events
+        .into_iter()
+        .filter(|gatya_event| gatya_event.matches(filter, now))
+        .collect();
+    match filter.sort {
+        EventSort::File => {}
+        EventSort::Start => gatya_events.sort_by_key(|gatya_event| gatya_event.start),
+        EventSort::End => gatya_events.sort_by_key(|gatya_event| gatya_event.end),
+        EventSort::GatyaId => gatya_events.sort_by_key(|gatya_event| gatya_event.gatya_id),
+    }
+    gatya_events
 }
 
-pub fn get_gatya_event_from_index(data: &[GatyaEvent], index: u32) -> GatyaEvent {
+pub fn get_gatya_event(data: &[GatyaEvent], gatya_id: u32) -> Option<GatyaEvent> {
     for gatya_event in data.iter() {
-        if gatya_event.index == index {
-            return gatya_event.clone();
+        if gatya_event.gatya_id == gatya_id {
+            return Some(gatya_event.clone());
         }
     }
-    panic!("Gatya event not found");
+    None
+}
+
+// latest.txt only changes with game updates, so it is fetched once per run rather than for
+// every file that checks its version
+static LATEST_TXT: tokio::sync::Mutex<Option<String>> = tokio::sync::Mutex::const_new(None);
+
+async fn get_latest_txt() -> error::Result<String> {
+    let mut latest_txt = LATEST_TXT.lock().await;
+    if let Some(body) = latest_txt.as_ref() {
+        return Ok(body.clone());
+    }
+    let url: String = format!("{}/latest.txt", config::get().bcdata_url);
+    let body: String = http::get_text(http::get_client().get(&url)).await?;
+    *latest_txt = Some(body.clone());
+    Ok(body)
+}
+
+// Lets tests act like the game updated while the process is running
+#[cfg(test)]
+pub async fn clear_latest_txt() {
+    *LATEST_TXT.lock().await = None;
 }
 
-async fn get_latest_game_data_version(cc: &str) -> String {
-    let url: String =
-        "https://raw.githubusercontent.com/fieryhenry/BCData/master/latest.txt".to_string();
-    let client: reqwest::Client = reqwest::Client::new();
-    let res: reqwest::Response = client.get(&url).send().await.unwrap();
-    let body: String = res.text().await.unwrap();
+pub async fn get_latest_game_data_version(cc: &str) -> error::Result<String> {
+    let line: usize = match cc {
+        "en" => 0,
+        "jp" => 1,
+        "kr" => 2,
+        "tw" => 3,
+        _ => return Err(Error::UnknownRegion(cc.to_string())),
+    };
+    let body: String = get_latest_txt().await?;
     let lines: Vec<&str> = body.split('\n').collect();
-    match cc {
-        "en" => lines[0].to_string(),
-        "jp" => lines[1].to_string(),
-        "kr" => lines[2].to_string(),
-        "tw" => lines[3].to_string(),
-        _ => panic!("Invalid country code"),
-    }
-}
-
-pub async fn get_gatya_cat_data(cc: &str, force: bool) -> Vec<Vec<i32>> {
-    let file_path: String = format!("data/gatya_{}.csv", cc);
-    let body: String;
-    if std::path::Path::new(&file_path).exists() && !force {
-        body = std::fs::read_to_string(file_path).unwrap();
-    } else {
-        let latest_game_data_version: String = get_latest_game_data_version(cc).await;
-        let url: String = format!(
-            "https://raw.githubusercontent.com/fieryhenry/BCData/master/{}/DataLocal/GatyaDataSetR1.csv",
-            latest_game_data_version
-        );
-        let client: reqwest::Client = reqwest::Client::new();
-        let res: reqwest::Response = client.get(&url).send().await.unwrap();
-        body = res.text().await.unwrap();
+    match lines.get(line) {
+        Some(version) if !version.trim().is_empty() => Ok(version.trim().to_string()),
+        _ => Err(Error::Parse(format!(
+            "no game data version for {} in latest.txt",
+            cc
+        ))),
+    }
+}
+
+// Game data is only downloaded again when BCData has a newer version than the cached copy
+async fn get_game_data_file(
+    cc: &str,
+    file_name: &str,
+    local_name: &str,
+    force: bool,
+    offline: bool,
+) -> error::Result<String> {
+    let file_path: PathBuf = cache::get_data_path(local_name);
+    if offline {
+        return cache::read_cached(&file_path);
+    }
 
-        std::fs::write(file_path, body.clone()).unwrap();
+    let latest_game_data_version: String = match get_latest_game_data_version(cc).await {
+        Ok(version) => version,
+        Err(err) if file_path.exists() && !force => {
+            eprintln!(
+                "Could not check for new game data, using cached data. {}",
+                err
+            );
+            return cache::read_cached(&file_path);
+        }
+        Err(err) => return Err(err),
+    };
+    if file_path.exists() && !force {
+        let cached_version: Option<String> =
+            cache::read_metadata(&file_path).and_then(|metadata| metadata.version);
+        if cached_version.as_deref() == Some(latest_game_data_version.as_str()) {
+            return cache::read_cached(&file_path);
+        }
     }
-    let records: Vec<Vec<String>> = parse_csv(body, ',');
+
+    eprintln!(
+        "Downloading {} ({})...",
+        file_name, latest_game_data_version
+    );
+    let url: String = format!(
+        "{}/{}/DataLocal/{}",
+        config::get().bcdata_url,
+        latest_game_data_version,
+        file_name
+    );
+    let body: String = http::get_text(http::get_client().get(&url)).await?;
+
+    cache::write_cached(&file_path, body.as_bytes(), Some(&latest_game_data_version))?;
+    Ok(body)
+}
+
+pub async fn get_gatya_cat_data(
+    cc: &str,
+    gatya_type: GatyaType,
+    force: bool,
+    offline: bool,
+) -> error::Result<Vec<Vec<i32>>> {
+    let (dataset_name, local_name) =
+        match (gatya_type.get_dataset_name(), gatya_type.get_local_name(cc)) {
+            (Some(dataset_name), Some(local_name)) => (dataset_name, local_name),
+            _ => {
+                return Err(Error::Parse(format!(
+                    "no gatya dataset is known for {:?} events",
+                    gatya_type
+                )))
+            }
+        };
+    let body: String = get_game_data_file(cc, dataset_name, &local_name, force, offline).await?;
+    let records: Vec<Vec<String>> = parse_csv(body, ',')?;
     let mut gatya_cat_data: Vec<Vec<i32>> = Vec::new();
     for record in records.iter() {
         let mut row: Vec<i32> = Vec::new();
         for field in record.iter() {
-            let result = field.parse::<i32>();
-            if result.is_err() {
-                continue;
-            }
-            let value: i32 = result.unwrap();
+            let value: i32 = match field.parse::<i32>() {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
             if value == -1 {
                 break;
             }
@@ -133,93 +444,557 @@ pub async fn get_gatya_cat_data(cc: &str, force: bool) -> Vec<Vec<i32>> {
         }
         gatya_cat_data.push(row);
     }
-    gatya_cat_data
+    Ok(gatya_cat_data)
 }
 
-pub async fn get_unitbuy_cat_data(cc: &str, force: bool) -> Vec<Vec<i32>> {
-    let file_path: String = format!("data/unitbuy_{}.csv", cc);
-    let body: String;
-    if std::path::Path::new(&file_path).exists() && !force {
-        body = std::fs::read_to_string(file_path).unwrap();
-    } else {
-        let latest_game_data_version: String = get_latest_game_data_version(cc).await;
-        let url: String = format!(
-            "https://raw.githubusercontent.com/fieryhenry/BCData/master/{}/DataLocal/unitbuy.csv",
-            latest_game_data_version
-        );
-        let client: reqwest::Client = reqwest::Client::new();
-        let res: reqwest::Response = client.get(&url).send().await.unwrap();
-        body = res.text().await.unwrap();
+// Column 14 of unitbuy.csv
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnitRarity {
+    Normal,
+    Special,
+    Rare,
+    SuperRare,
+    UberRare,
+    LegendRare,
+    Other(i32),
+    // the unitbuy.csv row couldn't be read
+    Unknown,
+}
 
-        std::fs::write(file_path, body.clone()).unwrap();
+impl UnitRarity {
+    fn from_code(code: i32) -> UnitRarity {
+        match code {
+            0 => UnitRarity::Normal,
+            1 => UnitRarity::Special,
+            2 => UnitRarity::Rare,
+            3 => UnitRarity::SuperRare,
+            4 => UnitRarity::UberRare,
+            5 => UnitRarity::LegendRare,
+            _ => UnitRarity::Other(code),
+        }
     }
 
-    let records: Vec<Vec<String>> = parse_csv(body, ',');
-    let mut unitbuy_cat_data: Vec<Vec<i32>> = Vec::new();
-    for record in records.iter() {
-        let mut row: Vec<i32> = Vec::new();
-        for field in record.iter() {
-            let resut = field.parse::<i32>();
-            if resut.is_err() {
-                continue;
-            }
-            let value = resut.unwrap();
-            row.push(value);
+    // Index into the slot data, only the four gacha rarities have one
+    pub fn get_slot_index(&self) -> Option<usize> {
+        match self {
+            UnitRarity::Rare => Some(0),
+            UnitRarity::SuperRare => Some(1),
+            UnitRarity::UberRare => Some(2),
+            UnitRarity::LegendRare => Some(3),
+            _ => None,
         }
-        unitbuy_cat_data.push(row);
     }
-    unitbuy_cat_data
+}
+
+// The only unitbuy.csv column read, the others are costs and unlock conditions
+const UNITBUY_RARITY_COLUMN: usize = 13;
+
+// One row of unitbuy.csv, rows are in cat ID order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnitBuy {
+    pub rarity: UnitRarity,
+}
+
+fn parse_unitbuy_row(row: &[String]) -> Result<UnitBuy, String> {
+    Ok(UnitBuy {
+        rarity: UnitRarity::from_code(parse_field(row, UNITBUY_RARITY_COLUMN, "rarity")?),
+    })
+}
+
+// A bad row is kept with an Unknown rarity so the cats after it keep their IDs
+pub async fn get_unitbuy_cat_data(
+    cc: &str,
+    force: bool,
+    offline: bool,
+) -> error::Result<Vec<UnitBuy>> {
+    let body: String = get_game_data_file(
+        cc,
+        "unitbuy.csv",
+        &format!("unitbuy_{}.csv", cc),
+        force,
+        offline,
+    )
+    .await?;
+    parse_unitbuy(body)
+}
+
+fn parse_unitbuy(body: String) -> error::Result<Vec<UnitBuy>> {
+    let records: Vec<Vec<String>> = parse_csv(body, ',')?;
+    let mut unitbuy_cat_data: Vec<UnitBuy> = Vec::new();
+    for (cat_id, record) in records.iter().enumerate() {
+        let unit_buy: UnitBuy = match parse_unitbuy_row(record) {
+            Ok(unit_buy) => unit_buy,
+            Err(err) => {
+                eprintln!(
+                    "Skipping malformed unitbuy.csv row for cat {}: {}",
+                    cat_id, err
+                );
+                UnitBuy {
+                    rarity: UnitRarity::Unknown,
+                }
+            }
+        };
+        unitbuy_cat_data.push(unit_buy);
+    }
+    Ok(unitbuy_cat_data)
+}
+
+pub struct GatyaPool {
+    pub slots: Vec<Vec<i32>>,
+    // units in the banner that can't be rolled as one of the four gacha rarities
+    pub skipped: Vec<(i32, UnitRarity)>,
 }
 
 pub fn get_gatya_slot_data(
     gatya_id: i32,
+    gatya_type: GatyaType,
     gatya_cat_data: Vec<Vec<i32>>,
-    unit_buy_cat_data: Vec<Vec<i32>>,
-) -> Vec<Vec<i32>> {
-    let gatya_cat_data: Vec<i32> = gatya_cat_data[gatya_id as usize].to_vec();
-    let mut gatya_slot_data: Vec<Vec<i32>> = Vec::new();
-    for _ in 0..4 {
-        gatya_slot_data.push(Vec::new());
-    }
+    unit_buy_cat_data: &[UnitBuy],
+) -> error::Result<GatyaPool> {
+    let gatya_cat_data: Vec<i32> = match gatya_cat_data.get(gatya_id as usize) {
+        Some(row) => row.to_vec(),
+        None => {
+            return Err(Error::Parse(format!(
+                "no {} data for gatya id {}",
+                gatya_type.get_dataset_name().unwrap_or("gatya"),
+                gatya_id
+            )))
+        }
+    };
+    let mut pool: GatyaPool = GatyaPool {
+        slots: vec![Vec::new(); 4],
+        skipped: Vec::new(),
+    };
 
     for cat_id in gatya_cat_data {
-        let rarity: i32 = unit_buy_cat_data[cat_id as usize][13];
-        if rarity == 0 || rarity == 1 {
-            continue;
-        }
-        if rarity == 2 {
-            gatya_slot_data[0].push(cat_id);
-        } else if rarity == 3 {
-            gatya_slot_data[1].push(cat_id);
-        } else if rarity == 4 {
-            gatya_slot_data[2].push(cat_id);
-        } else if rarity == 5 {
-            gatya_slot_data[3].push(cat_id);
+        let rarity: UnitRarity = match usize::try_from(cat_id)
+            .ok()
+            .and_then(|cat_id| unit_buy_cat_data.get(cat_id))
+        {
+            Some(unit_buy) => unit_buy.rarity,
+            None => {
+                return Err(Error::Parse(format!(
+                    "cat {} in gatya id {} has no unitbuy.csv row ({} rows)",
+                    cat_id,
+                    gatya_id,
+                    unit_buy_cat_data.len()
+                )))
+            }
+        };
+        match rarity.get_slot_index() {
+            Some(slot_index) => pool.slots[slot_index].push(cat_id),
+            None => pool.skipped.push((cat_id, rarity)),
         }
     }
 
-    gatya_slot_data
+    Ok(pool)
+}
+
+// Every rarity the banner can roll needs at least one cat, otherwise there is no slot to pick
+pub fn check_pool_sizes(
+    gatya_event: &GatyaEvent,
+    gatya_slot_data: &[Vec<i32>],
+) -> error::Result<()> {
+    let (super_rare_chance, _, _) = gatya_event.get_rarity_thresholds();
+    let chances: [(u32, &str); 4] = [
+        (super_rare_chance, "rare"),
+        (gatya_event.super_rare_chance, "super rare"),
+        (gatya_event.uber_rare_chance, "uber rare"),
+        (gatya_event.legend_rare_chance, "legend rare"),
+    ];
+    for (slot_index, (chance, name)) in chances.iter().enumerate() {
+        if *chance > 0 && gatya_slot_data[slot_index].is_empty() {
+            return Err(Error::Parse(format!(
+                "gatya id {} has a {}% {} chance but no {} cats",
+                gatya_event.gatya_id,
+                *chance as f64 / 100.0,
+                name,
+                name
+            )));
+        }
+    }
+    // step-up banners guarantee an uber on their last step
+    let has_guaranteed_uber: bool =
+        gatya_event.guaranteed || gatya_event.flags.contains(GatyaFlags::STEP_UP);
+    if has_guaranteed_uber && gatya_slot_data[2].is_empty() {
+        return Err(Error::Parse(format!(
+            "gatya id {} has a guaranteed uber but no uber rare cats",
+            gatya_event.gatya_id
+        )));
+    }
+    Ok(())
 }
 
-fn get_slot_from_id(gatya_slot_data: Vec<Vec<i32>>, cat_id: i32) -> (i32, i32) {
+pub fn get_slot_from_id(gatya_slot_data: &[Vec<i32>], cat_id: i32) -> Option<(i32, i32)> {
     for (rarity, rarity_data) in gatya_slot_data.iter().enumerate() {
         for (slot_id, slot_data) in rarity_data.iter().enumerate() {
             if *slot_data == cat_id {
-                return (rarity as i32, slot_id as i32);
+                return Some((rarity as i32, slot_id as i32));
             }
         }
     }
-    panic!("Cat ID not found in gatya slot data");
+    None
 }
 
-pub fn get_cat_list_from_ids(gatya_slot_data: Vec<Vec<i32>>, cat_ids: Vec<i32>) -> Vec<(u32, u32)> {
+pub fn get_cat_list_from_ids(
+    gatya_slot_data: Vec<Vec<i32>>,
+    cat_ids: Vec<i32>,
+) -> error::Result<Vec<(u32, u32)>> {
     let mut cat_list: Vec<(u32, u32)> = Vec::new();
     for cat_id in cat_ids.iter() {
         if *cat_id == BLANK_SLOT_USER {
-            cat_list.push((BLANK_SLOT, 0))
+            cat_list.push((BLANK_SLOT, 0));
+            continue;
         }
-        let (rarity, slot_id) = get_slot_from_id(gatya_slot_data.clone(), *cat_id);
+        let (rarity, slot_id) = match get_slot_from_id(&gatya_slot_data, *cat_id) {
+            Some(slot) => slot,
+            None => return Err(Error::MissingCat(*cat_id)),
+        };
         cat_list.push((rarity as u32, slot_id as u32));
     }
-    cat_list
+    Ok(cat_list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVENT_LINE: &str = "20240801\t1100\t20240815\t1100\t0\t999999\t0\t0\t1\t1\t905\t150\t0\t0\t0\t0\t6970\t0\t2500\t0\t500\t0\t30\t0\tLegend Rare Festival";
+
+    #[test]
+    fn parses_a_gatya_line() {
+        let data: String = format!("[start]\n{}\n[end]\n", EVENT_LINE);
+        let gatya_events: Vec<GatyaEvent> = parse_gatya_events(data).unwrap();
+        assert_eq!(gatya_events.len(), 1);
+        let gatya_event: &GatyaEvent = &gatya_events[0];
+        assert_eq!(gatya_event.gatya_id, 905);
+        assert_eq!(gatya_event.banner_txt, "Legend Rare Festival");
+        assert_eq!(
+            gatya_event.start,
+            NaiveDate::from_ymd_opt(2024, 8, 1)
+                .unwrap()
+                .and_hms_opt(11, 0, 0)
+                .unwrap()
+        );
+        assert_eq!(gatya_event.rare_chance, 6970);
+        assert_eq!(gatya_event.super_rare_chance, 2500);
+        assert_eq!(gatya_event.uber_rare_chance, 500);
+        assert_eq!(gatya_event.legend_rare_chance, 30);
+        assert_eq!(gatya_event.get_rarity_thresholds(), (6970, 9470, 9970));
+    }
+
+    #[test]
+    fn parses_times_without_leading_zeros() {
+        let line: String = EVENT_LINE.replacen("\t1100\t", "\t0\t", 1);
+        let gatya_events: Vec<GatyaEvent> = parse_gatya_events(line).unwrap();
+        assert_eq!(
+            gatya_events[0].start,
+            NaiveDate::from_ymd_opt(2024, 8, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        );
+    }
+
+    // A line with two gatya entries, each one 15 columns after the last
+    fn get_two_entry_line() -> String {
+        let mut fields: Vec<&str> = EVENT_LINE.split('\t').collect();
+        fields[9] = "2";
+        let second_entry: [&str; 15] = [
+            "906",
+            "150",
+            "0",
+            "4",
+            "0",
+            "0",
+            "6500",
+            "0",
+            "2600",
+            "0",
+            "900",
+            "1",
+            "0",
+            "0",
+            "Step-Up Festival",
+        ];
+        fields.extend_from_slice(&second_entry);
+        fields.join("\t")
+    }
+
+    #[test]
+    fn parses_every_entry_on_a_line() {
+        let gatya_events: Vec<GatyaEvent> = parse_gatya_events(get_two_entry_line()).unwrap();
+        assert_eq!(gatya_events.len(), 2);
+        assert_eq!(gatya_events[0].gatya_id, 905);
+        assert_eq!(gatya_events[0].banner_txt, "Legend Rare Festival");
+        assert_eq!(gatya_events[1].gatya_id, 906);
+        assert_eq!(gatya_events[1].banner_txt, "Step-Up Festival");
+        assert_eq!(gatya_events[1].uber_rare_chance, 900);
+        assert_eq!(gatya_events[1].start, gatya_events[0].start);
+    }
+
+    #[test]
+    fn keeps_entries_before_a_missing_one() {
+        let line: String = EVENT_LINE.replacen("\t1\t905\t", "\t2\t905\t", 1);
+        let gatya_ids: Vec<u32> = parse_gatya_events(line)
+            .unwrap()
+            .iter()
+            .map(|gatya_event| gatya_event.gatya_id)
+            .collect();
+        assert_eq!(gatya_ids, vec![905]);
+    }
+
+    #[test]
+    fn keeps_valid_entries_around_a_malformed_one() {
+        let line: String = get_two_entry_line().replacen("\t6970\t", "\t9970\t", 1);
+        let gatya_ids: Vec<u32> = parse_gatya_events(line)
+            .unwrap()
+            .iter()
+            .map(|gatya_event| gatya_event.gatya_id)
+            .collect();
+        assert_eq!(gatya_ids, vec![906]);
+    }
+
+    #[test]
+    fn decodes_price_flags_and_guaranteed() {
+        let line: String = get_two_entry_line();
+        let gatya_events: Vec<GatyaEvent> = parse_gatya_events(line).unwrap();
+        assert_eq!(gatya_events[0].price, 150);
+        assert!(!gatya_events[0].guaranteed);
+        assert!(gatya_events[0].get_tags().is_empty());
+        assert!(gatya_events[1].guaranteed);
+        assert!(gatya_events[1].flags.contains(GatyaFlags::STEP_UP));
+        assert_eq!(gatya_events[1].get_tags(), vec!["guaranteed", "step-up"]);
+    }
+
+    #[test]
+    fn only_shows_known_flags() {
+        let line: String = EVENT_LINE.replacen("\t150\t0\t0\t", "\t150\t0\t3\t", 1);
+        let gatya_events: Vec<GatyaEvent> = parse_gatya_events(line).unwrap();
+        assert_eq!(gatya_events[0].flags.bits(), 3);
+        assert!(gatya_events[0].get_tags().is_empty());
+    }
+
+    #[test]
+    fn rejects_guaranteed_and_step_up_banners_without_ubers() {
+        let line: String = get_two_entry_line();
+        let gatya_events: Vec<GatyaEvent> = parse_gatya_events(line).unwrap();
+        let slots: Vec<Vec<i32>> = vec![vec![0], vec![1], vec![2], vec![3]];
+        let no_ubers: Vec<Vec<i32>> = vec![vec![0], vec![1], Vec::new(), vec![3]];
+        assert!(check_pool_sizes(&gatya_events[1], &slots).is_ok());
+
+        // no uber chance, so only the guarantee needs an uber
+        let mut step_up: GatyaEvent = gatya_events[1].clone();
+        step_up.guaranteed = false;
+        step_up.rare_chance += step_up.uber_rare_chance;
+        step_up.uber_rare_chance = 0;
+        assert!(check_pool_sizes(&step_up, &no_ubers).is_err());
+        step_up.flags = GatyaFlags::empty();
+        assert!(check_pool_sizes(&step_up, &no_ubers).is_ok());
+        step_up.guaranteed = true;
+        assert!(check_pool_sizes(&step_up, &no_ubers).is_err());
+    }
+
+    fn get_date(day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 8, day)
+            .unwrap()
+            .and_hms_opt(11, 0, 0)
+            .unwrap()
+    }
+
+    fn get_filter_events() -> Vec<GatyaEvent> {
+        let template: GatyaEvent = parse_gatya_events(EVENT_LINE.to_string()).unwrap()[0].clone();
+        [
+            (3, 1, 5, "Past Festival"),
+            (1, 8, 20, "Running Fest"),
+            (2, 25, 30, "Upcoming Festival"),
+        ]
+        .iter()
+        .map(|(gatya_id, start, end, banner_txt)| GatyaEvent {
+            gatya_id: *gatya_id,
+            start: get_date(*start),
+            end: get_date(*end),
+            banner_txt: banner_txt.to_string(),
+            ..template.clone()
+        })
+        .collect()
+    }
+
+    fn get_filtered_ids(filter: &EventFilter) -> Vec<u32> {
+        filter_events(get_filter_events(), filter, get_date(10))
+            .iter()
+            .map(|gatya_event| gatya_event.gatya_id)
+            .collect()
+    }
+
+    #[test]
+    fn filters_events() {
+        assert_eq!(get_filtered_ids(&EventFilter::default()), vec![3, 1, 2]);
+        let status = |status: EventStatus| EventFilter {
+            status: Some(status),
+            ..Default::default()
+        };
+        assert_eq!(get_filtered_ids(&status(EventStatus::Past)), vec![3]);
+        assert_eq!(get_filtered_ids(&status(EventStatus::Running)), vec![1]);
+        assert_eq!(get_filtered_ids(&status(EventStatus::Upcoming)), vec![2]);
+        let text: EventFilter = EventFilter {
+            text: Some("FESTIVAL".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(get_filtered_ids(&text), vec![3, 2]);
+        let gatya_id: EventFilter = EventFilter {
+            gatya_id: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(get_filtered_ids(&gatya_id), vec![2]);
+    }
+
+    #[test]
+    fn sorts_events() {
+        let sort = |sort: EventSort| EventFilter {
+            sort,
+            ..Default::default()
+        };
+        assert_eq!(get_filtered_ids(&sort(EventSort::Start)), vec![3, 1, 2]);
+        assert_eq!(get_filtered_ids(&sort(EventSort::GatyaId)), vec![1, 2, 3]);
+        let mut gatya_events: Vec<GatyaEvent> = get_filter_events();
+        gatya_events[0].end = get_date(31);
+        let ids: Vec<u32> = filter_events(gatya_events, &sort(EventSort::End), get_date(10))
+            .iter()
+            .map(|gatya_event| gatya_event.gatya_id)
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn skips_malformed_lines() {
+        let bad_date: String = EVENT_LINE.replacen("20240801", "2024-08-01", 1);
+        let bad_chances: String = EVENT_LINE.replacen("\t6970\t", "\t9970\t", 1);
+        let data: String = format!(
+            "[start]\n{}\n{}\n{}\nshort\tline\n[end]\n",
+            bad_date, EVENT_LINE, bad_chances
+        );
+        let gatya_events: Vec<GatyaEvent> = parse_gatya_events(data).unwrap();
+        assert_eq!(gatya_events.len(), 1);
+        assert_eq!(gatya_events[0].rare_chance, 6970);
+    }
+
+    // A unitbuy.csv row with the given rarity code in column 14
+    fn get_unitbuy_row(rarity: &str) -> String {
+        let mut row: Vec<&str> = vec!["0"; 20];
+        row[UNITBUY_RARITY_COLUMN] = rarity;
+        row.join(",")
+    }
+
+    #[test]
+    fn parses_unitbuy_rarities() {
+        let data: String = ["2", "3", "4", "5", "0", "9"]
+            .iter()
+            .map(|rarity| get_unitbuy_row(rarity))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let rarities: Vec<UnitRarity> = parse_unitbuy(data)
+            .unwrap()
+            .iter()
+            .map(|unit_buy| unit_buy.rarity)
+            .collect();
+        assert_eq!(
+            rarities,
+            vec![
+                UnitRarity::Rare,
+                UnitRarity::SuperRare,
+                UnitRarity::UberRare,
+                UnitRarity::LegendRare,
+                UnitRarity::Normal,
+                UnitRarity::Other(9),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_cat_ids_after_bad_unitbuy_rows() {
+        let data: String = format!(
+            "{}\n0,0,0\n{}\n{}\n",
+            get_unitbuy_row("2"),
+            get_unitbuy_row("x"),
+            get_unitbuy_row("4")
+        );
+        let unitbuy_cat_data: Vec<UnitBuy> = parse_unitbuy(data).unwrap();
+        let rarities: Vec<UnitRarity> = unitbuy_cat_data
+            .iter()
+            .map(|unit_buy| unit_buy.rarity)
+            .collect();
+        assert_eq!(
+            rarities,
+            vec![
+                UnitRarity::Rare,
+                UnitRarity::Unknown,
+                UnitRarity::Unknown,
+                UnitRarity::UberRare,
+            ]
+        );
+
+        let pool: GatyaPool = get_gatya_slot_data(
+            0,
+            GatyaType::Rare,
+            vec![vec![0, 1, 2, 3]],
+            &unitbuy_cat_data,
+        )
+        .unwrap();
+        assert_eq!(pool.slots, vec![vec![0], vec![], vec![3], vec![]]);
+        assert_eq!(
+            pool.skipped,
+            vec![(1, UnitRarity::Unknown), (2, UnitRarity::Unknown)]
+        );
+    }
+
+    #[test]
+    fn rejects_cats_without_a_unitbuy_row() {
+        let unitbuy_cat_data: Vec<UnitBuy> = vec![UnitBuy {
+            rarity: UnitRarity::Rare,
+        }];
+        assert!(
+            get_gatya_slot_data(0, GatyaType::Rare, vec![vec![0, 5]], &unitbuy_cat_data).is_err()
+        );
+        assert!(get_gatya_slot_data(1, GatyaType::Rare, vec![vec![0]], &unitbuy_cat_data).is_err());
+    }
+
+    #[tokio::test]
+    async fn downloads_game_data_again_only_for_a_new_version() {
+        use crate::mock_server::{self, tests::start_test_server, tests::TEST_LOCK};
+
+        start_test_server();
+        let _lock = TEST_LOCK.lock().await;
+        clear_latest_txt().await;
+        let file_path: PathBuf = cache::get_data_path("unitbuy_kr.csv");
+        let _ = std::fs::remove_file(&file_path);
+
+        let body: String = get_game_data_file("kr", "unitbuy.csv", "unitbuy_kr.csv", false, false)
+            .await
+            .unwrap();
+        assert!(!body.is_empty());
+        // a cached copy of the latest version is used as-is
+        cache::write_cached(&file_path, b"cached", Some("13.0.0kr")).unwrap();
+        let body: String = get_game_data_file("kr", "unitbuy.csv", "unitbuy_kr.csv", false, false)
+            .await
+            .unwrap();
+        assert_eq!(body, "cached");
+
+        // latest.txt was only read once, so the update shows up on the next run
+        mock_server::set_game_version("13.1.0");
+        let body: String = get_game_data_file("kr", "unitbuy.csv", "unitbuy_kr.csv", false, false)
+            .await
+            .unwrap();
+        assert_eq!(body, "cached");
+        clear_latest_txt().await;
+        let body: String = get_game_data_file("kr", "unitbuy.csv", "unitbuy_kr.csv", false, false)
+            .await
+            .unwrap();
+        mock_server::set_game_version(mock_server::MOCK_GAME_VERSION);
+        clear_latest_txt().await;
+        assert_ne!(body, "cached");
+        let version: Option<String> =
+            cache::read_metadata(&file_path).and_then(|metadata| metadata.version);
+        assert_eq!(version.as_deref(), Some("13.1.0kr"));
+    }
 }
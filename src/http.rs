@@ -0,0 +1,188 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::error::{self, Error};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_ATTEMPTS: u32 = 4;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_ERROR_LENGTH: usize = 200;
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+pub fn get_client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("failed to build http client")
+    })
+}
+
+// Pulls a readable message out of an error response, which is usually JSON
+fn get_error_message(body: &str) -> String {
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(body) {
+        for key in ["message", "errorMessage", "error"] {
+            if let Some(message) = json[key].as_str() {
+                return message.to_string();
+            }
+        }
+        if let Some(status_code) = json["statusCode"].as_i64() {
+            return format!("status code {}", status_code);
+        }
+    }
+    let body: &str = body.trim();
+    if body.chars().count() > MAX_ERROR_LENGTH {
+        let truncated: String = body.chars().take(MAX_ERROR_LENGTH).collect();
+        return format!("{}...", truncated);
+    }
+    body.to_string()
+}
+
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+fn is_retryable_error(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_connect()
+}
+
+// Sends a request, retrying GETs with exponential backoff on timeouts, connection errors, 5xx and 429.
+// Other methods are sent once, since a POST that reached the server may already have created something
+pub async fn send(request: reqwest::RequestBuilder) -> error::Result<reqwest::Response> {
+    let (client, request) = request.build_split();
+    let request: reqwest::Request = request?;
+    if request.method() != reqwest::Method::GET {
+        return Ok(client.execute(request).await?);
+    }
+    let mut backoff: Duration = INITIAL_BACKOFF;
+    let mut attempt: u32 = 1;
+    loop {
+        let current: reqwest::Request = match request.try_clone() {
+            Some(current) => current,
+            None => return Ok(client.execute(request).await?),
+        };
+        let result: Result<reqwest::Response, reqwest::Error> = client.execute(current).await;
+        let retry: bool = match &result {
+            Ok(res) => is_retryable_status(res.status()),
+            Err(err) => is_retryable_error(err),
+        };
+        if !retry || attempt >= MAX_ATTEMPTS {
+            return Ok(result?);
+        }
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+pub async fn get_response(
+    request: reqwest::RequestBuilder,
+) -> error::Result<(reqwest::header::HeaderMap, Vec<u8>)> {
+    let res: reqwest::Response = send(request).await?;
+    let status: reqwest::StatusCode = res.status();
+    let url: String = res
+        .url()
+        .as_str()
+        .split('?')
+        .next()
+        .unwrap_or("")
+        .to_string();
+    let headers: reqwest::header::HeaderMap = res.headers().clone();
+    let body: Vec<u8> = res.bytes().await?.to_vec();
+    if !status.is_success() {
+        return Err(Error::Http {
+            status: status.as_u16(),
+            url,
+            message: get_error_message(&String::from_utf8_lossy(&body)),
+        });
+    }
+
+    let content_length: Option<usize> = headers
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    if let Some(content_length) = content_length {
+        if content_length != body.len() {
+            return Err(Error::Verification(format!(
+                "{} sent {} of {} bytes",
+                url,
+                body.len(),
+                content_length
+            )));
+        }
+    }
+    Ok((headers, body))
+}
+
+pub async fn get_text(request: reqwest::RequestBuilder) -> error::Result<String> {
+    let (_, body) = get_response(request).await?;
+    Ok(String::from_utf8_lossy(&body).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    // Answers every request with a 503 and counts them
+    async fn start_failing_server() -> (SocketAddr, Arc<AtomicU32>) {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        let requests: Arc<AtomicU32> = Arc::new(AtomicU32::new(0));
+        let counter: Arc<AtomicU32> = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut buffer: [u8; 4096] = [0; 4096];
+                let _ = stream.read(&mut buffer).await;
+                let _ = stream
+                    .write_all(
+                        b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    )
+                    .await;
+            }
+        });
+        (addr, requests)
+    }
+
+    #[test]
+    fn retries_server_errors_and_rate_limits() {
+        assert!(is_retryable_status(
+            reqwest::StatusCode::SERVICE_UNAVAILABLE
+        ));
+        assert!(is_retryable_status(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable_status(reqwest::StatusCode::BAD_REQUEST));
+        assert!(!is_retryable_status(reqwest::StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn never_retries_posts() {
+        let (addr, requests) = start_failing_server().await;
+        let request: reqwest::RequestBuilder = get_client()
+            .post(format!("http://{}/v1/tokens", addr))
+            .body("{}");
+        match get_response(request).await {
+            Err(Error::Http { status, .. }) => assert_eq!(status, 503),
+            other => panic!("expected a 503 response, got {:?}", other.map(|_| ())),
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_gets() {
+        let (addr, requests) = start_failing_server().await;
+        let request: reqwest::RequestBuilder =
+            get_client().get(format!("http://{}/latest.txt", addr));
+        assert!(get_response(request).await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), MAX_ATTEMPTS);
+    }
+}
@@ -1,41 +1,184 @@
+mod account;
+mod cache;
+mod cat_names;
+mod cli;
+mod config;
+mod data_bundle;
+mod error;
 mod event_data;
+mod game_pack;
 mod gatya_data;
+mod http;
+mod mock_server;
+mod output;
+mod pattern_file;
+mod repl;
+mod simulator;
+mod snapshots;
+mod tui;
 
-use std::{io::Write, time::Instant};
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use clap::Parser;
+use std::{
+    collections::HashMap,
+    io::Write,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
-async fn get_event_data(cc: &str, force: bool) -> String {
-    let file_path: String = format!("data/gatya_{}.tsv", cc);
-    if std::path::Path::new(&file_path).exists() && !force {
-        let data: String = std::fs::read_to_string(file_path).unwrap();
-        return data;
+async fn get_event_data(cc: &str, force: bool) -> error::Result<String> {
+    let file_path: PathBuf = cache::get_data_path(&format!("gatya_{}.tsv", cc));
+    if file_path.exists() && !force {
+        if let Some(fetched_at) = cache::read_metadata(&file_path).and_then(|m| m.fetched_at) {
+            eprintln!(
+                "Using event data from {}",
+                fetched_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+            );
+        }
+        return cache::read_cached(&file_path);
+    }
+    if file_path.exists() {
+        // keep what was there before in case it was never archived
+        let old_data: String = cache::read_cached(&file_path)?;
+        let fetched_at: DateTime<Utc> = cache::read_metadata(&file_path)
+            .and_then(|metadata| metadata.fetched_at)
+            .unwrap_or_else(Utc::now);
+        snapshots::save_snapshot(cc, &old_data, fetched_at)?;
     }
-    let data = event_data::get_event_data(cc).await;
-    std::fs::write(file_path, data.clone()).unwrap();
-    data
+    let data = event_data::get_event_data(cc).await?;
+    cache::write_cached(&file_path, data.as_bytes(), None)?;
+    snapshots::save_snapshot(cc, &data, Utc::now())?;
+    Ok(data)
 }
 
-fn get_int_from_user(prompt: &str, default: Option<i32>) -> i32 {
+async fn load_event_data(cc: &str, offline: bool, update: bool) -> error::Result<String> {
+    std::fs::create_dir_all(cache::get_data_dir())?;
+    cache::check_legacy_data_dir();
+    if offline {
+        if update {
+            return Err(error::Error::InvalidInput(
+                "data can't be updated in offline mode".to_string(),
+            ));
+        }
+        data_bundle::check_offline_data(cc)?;
+    }
+    eprintln!("Getting event data...");
+    get_event_data(cc, update).await
+}
+
+// Lets past banners be picked from older copies of gatya.tsv
+fn select_event_source(cc: &str, data: String) -> error::Result<Vec<gatya_data::GatyaEvent>> {
+    let snapshots: Vec<snapshots::Snapshot> = snapshots::list_snapshots(cc)?;
+    if snapshots.len() < 2 {
+        return gatya_data::parse_gatya_events(data);
+    }
+    println!("1. Current event data");
+    for (i, snapshot) in snapshots.iter().enumerate() {
+        println!(
+            "{}. Snapshot from {}",
+            i + 2,
+            snapshot.taken_at.format("%Y-%m-%d %H:%M")
+        );
+    }
+    println!("{}. All snapshots", snapshots.len() + 2);
+    loop {
+        let input: i32 = get_int_from_user("Select event data (default 1): ", Some(1));
+        if input == 1 {
+            return gatya_data::parse_gatya_events(data);
+        }
+        if input >= 2 && input <= snapshots.len() as i32 + 1 {
+            let snapshot: &snapshots::Snapshot = &snapshots[(input - 2) as usize];
+            return gatya_data::parse_gatya_events(snapshots::read_snapshot(snapshot)?);
+        }
+        if input == snapshots.len() as i32 + 2 {
+            return snapshots::get_all_events(cc, data);
+        }
+        println!("Invalid input. Try again.");
+    }
+}
+
+// Exits once stdin is closed instead of asking again forever
+fn read_line_from_user(prompt: &str) -> String {
     print!("{}", prompt);
     std::io::stdout().flush().unwrap();
 
     let mut input: String = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
+    let read: usize = std::io::stdin().read_line(&mut input).unwrap_or(0);
+    if read == 0 {
+        eprintln!("\nNo more input.");
+        std::process::exit(1);
+    }
+    input.trim().to_string()
+}
 
-    let input: i32 = match input.trim().parse() {
-        Ok(num) => num,
-        Err(_) => match default {
-            Some(d) => return d,
-            None => {
-                println!("Invalid input. Try again.");
-                return get_int_from_user(prompt, default);
-            }
-        },
+fn get_int_from_user(prompt: &str, default: Option<i32>) -> i32 {
+    loop {
+        let input: String = read_line_from_user(prompt);
+        match input.parse() {
+            Ok(num) => return num,
+            Err(_) => match default {
+                Some(d) => return d,
+                None => println!("Invalid input. Try again."),
+            },
+        }
+    }
+}
+
+fn get_string_from_user(prompt: &str) -> String {
+    read_line_from_user(prompt)
+}
+
+fn select_event_filter() -> gatya_data::EventFilter {
+    let status: Option<gatya_data::EventStatus> = loop {
+        let input: i32 = get_int_from_user(
+            "Show events: 1. All 2. Running 3. Upcoming 4. Past (default 1): ",
+            Some(1),
+        );
+        match input {
+            1 => break None,
+            2 => break Some(gatya_data::EventStatus::Running),
+            3 => break Some(gatya_data::EventStatus::Upcoming),
+            4 => break Some(gatya_data::EventStatus::Past),
+            _ => println!("Invalid input. Try again."),
+        }
     };
-    input
+
+    let text: String = get_string_from_user("Search banner text (leave empty for any): ");
+    let gatya_id: i32 = get_int_from_user("Gatya ID (leave empty for any): ", Some(-1));
+
+    let sort: gatya_data::EventSort = loop {
+        let input: i32 = get_int_from_user(
+            "Sort by: 1. File order 2. Start date 3. End date 4. Gatya ID (default 1): ",
+            Some(1),
+        );
+        match input {
+            1 => break gatya_data::EventSort::File,
+            2 => break gatya_data::EventSort::Start,
+            3 => break gatya_data::EventSort::End,
+            4 => break gatya_data::EventSort::GatyaId,
+            _ => println!("Invalid input. Try again."),
+        }
+    };
+
+    gatya_data::EventFilter {
+        status,
+        text: if text.is_empty() { None } else { Some(text) },
+        gatya_id: if gatya_id >= 0 {
+            Some(gatya_id as u32)
+        } else {
+            None
+        },
+        sort,
+    }
 }
 
 fn ask_if_want_to_update_data() -> bool {
-    let input: i32 = get_int_from_user("Update Game Data? (1 for yes, 2 for no): ", None);
+    let input: i32 = get_int_from_user("Update event and game data? (1 for yes, 2 for no): ", None);
     match input {
         1 => true,
         2 => false,
@@ -46,43 +189,57 @@ fn ask_if_want_to_update_data() -> bool {
     }
 }
 
-async fn select_event(cc: &str) -> (gatya_data::GatyaEvent, bool) {
-    std::fs::create_dir_all("data").unwrap();
-
-    let force: bool = ask_if_want_to_update_data();
-
-    println!("Getting event data...");
-
-    let data: String = get_event_data(cc, force).await;
-    let gatya_events: Vec<gatya_data::GatyaEvent> = gatya_data::parse_gatya_events(data);
-    let valid_events: Vec<&gatya_data::GatyaEvent> = gatya_events
-        .iter()
-        .filter(|gatya_event| !gatya_event.banner_txt.is_empty())
-        .collect();
-    for (i, gatya_event) in valid_events.iter().enumerate() {
-        let start_time: String = gatya_event.start.clone();
-        let end_time: String = gatya_event.end.clone();
-
-        let start_time_parsed: String = format!(
-            "{}-{}-{}",
-            &start_time[0..4],
-            &start_time[4..6],
-            &start_time[6..8]
-        );
-        let end_time_parsed: String = format!(
-            "{}-{}-{}",
-            &end_time[0..4],
-            &end_time[4..6],
-            &end_time[6..8]
-        );
+fn print_events(gatya_events: &[gatya_data::GatyaEvent]) {
+    for (i, gatya_event) in gatya_events.iter().enumerate() {
+        let tags: Vec<String> = gatya_event.get_tags();
+        let tags_str: String = if tags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", tags.join(", "))
+        };
         println!(
-            "{}. {} - {}: {}",
+            "{}. {} - {}: {} (id {}, price {}){}",
             i + 1,
-            start_time_parsed,
-            end_time_parsed,
-            gatya_event.banner_txt
+            gatya_event.start.format("%Y-%m-%d"),
+            gatya_event.end.format("%Y-%m-%d"),
+            gatya_event.banner_txt,
+            gatya_event.gatya_id,
+            gatya_event.price,
+            tags_str
         );
     }
+}
+
+// Asks whether to update the data when `update` isn't given, and returns the answer with the event
+async fn select_event(
+    cc: &str,
+    offline: bool,
+    update: Option<bool>,
+) -> error::Result<(gatya_data::GatyaEvent, bool)> {
+    let update: bool = if offline {
+        false
+    } else {
+        update.unwrap_or_else(ask_if_want_to_update_data)
+    };
+
+    let data: String = load_event_data(cc, offline, update).await?;
+    let gatya_events: Vec<gatya_data::GatyaEvent> = select_event_source(cc, data)?;
+    let gatya_events: Vec<gatya_data::GatyaEvent> = gatya_events
+        .into_iter()
+        .filter(|gatya_event| !gatya_event.banner_txt.is_empty())
+        .collect();
+    let now: NaiveDateTime = Local::now().naive_local();
+    let valid_events: Vec<gatya_data::GatyaEvent> = loop {
+        let filter: gatya_data::EventFilter = select_event_filter();
+        let valid_events: Vec<gatya_data::GatyaEvent> =
+            gatya_data::filter_events(gatya_events.clone(), &filter, now);
+        if valid_events.is_empty() {
+            println!("No events match the filter. Try again.");
+            continue;
+        }
+        break valid_events;
+    };
+    print_events(&valid_events);
     let mut input: i32;
     loop {
         input = get_int_from_user("Select event: ", None);
@@ -93,10 +250,37 @@ async fn select_event(cc: &str) -> (gatya_data::GatyaEvent, bool) {
         break;
     }
 
-    let gatya_event: &gatya_data::GatyaEvent = valid_events[(input - 1) as usize];
-    println!("Selected event: {}", gatya_event.banner_txt);
+    let gatya_event: &gatya_data::GatyaEvent = &valid_events[(input - 1) as usize];
+    eprintln!("Selected event: {}", gatya_event.banner_txt);
 
-    (gatya_event.clone(), force)
+    Ok((gatya_event.clone(), update))
+}
+
+// Uses the first event with the given gatya ID, or asks for one if there isn't an ID
+async fn get_event(
+    cc: &str,
+    args: &cli::EventArgs,
+    offline: bool,
+) -> error::Result<gatya_data::GatyaEvent> {
+    let gatya_id: u32 = match args.gatya {
+        Some(gatya_id) => gatya_id,
+        None => {
+            let (gatya_event, _) = select_event(cc, offline, Some(args.update)).await?;
+            return Ok(gatya_event);
+        }
+    };
+    let data: String = load_event_data(cc, offline, args.update).await?;
+    let gatya_events: Vec<gatya_data::GatyaEvent> = gatya_data::parse_gatya_events(data)?;
+    match gatya_data::get_gatya_event(&gatya_events, gatya_id) {
+        Some(gatya_event) => {
+            eprintln!("Selected event: {}", gatya_event.banner_txt);
+            Ok(gatya_event)
+        }
+        None => Err(error::Error::InvalidInput(format!(
+            "there is no event with gatya id {}",
+            gatya_id
+        ))),
+    }
 }
 
 fn select_cc() -> String {
@@ -118,33 +302,197 @@ fn select_cc() -> String {
     }
 }
 
-fn select_cats() -> Vec<i32> {
+fn get_cc(args: &cli::RegionArgs) -> error::Result<String> {
+    match &args.region {
+        Some(cc) if is_valid_cc(cc) => Ok(cc.clone()),
+        Some(cc) => Err(error::Error::UnknownRegion(cc.clone())),
+        None => {
+            let cc: String = select_cc();
+            println!();
+            Ok(cc)
+        }
+    }
+}
+
+fn select_cat_by_name(
+    query: &str,
+    cat_names: &HashMap<i32, String>,
+    cat_ids: &[i32],
+) -> Option<i32> {
+    let matches: Vec<i32> = cat_names::find_cats_by_name(query, cat_names, cat_ids);
+    if matches.is_empty() {
+        println!("No cat in this banner matches {:?}. Try again.", query);
+        return None;
+    }
+    if matches.len() == 1 {
+        return Some(matches[0]);
+    }
+    for (i, cat_id) in matches.iter().enumerate() {
+        println!(
+            "{}. {}",
+            i + 1,
+            cat_names::get_cat_display(*cat_id, cat_names)
+        );
+    }
+    let input: i32 = get_int_from_user("Select cat (0 to cancel): ", Some(0));
+    if input < 1 || input > matches.len() as i32 {
+        return None;
+    }
+    Some(matches[(input - 1) as usize])
+}
+
+// The #numbers count through the whole pool, which is what `#n` cat inputs refer to
+fn get_cat_pool_lines(
+    gatya_slot_data: &[Vec<i32>],
+    cat_names: &HashMap<i32, String>,
+) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut counter: u32 = 0;
+    for (rarity, rarity_data) in gatya_slot_data.iter().enumerate() {
+        if rarity_data.is_empty() {
+            continue;
+        }
+        lines.push(format!("{}:", simulator::get_rarity_name(rarity as u32)));
+        for cat_id in rarity_data.iter() {
+            counter += 1;
+            lines.push(format!(
+                "  #{}. {}",
+                counter,
+                cat_names::get_cat_display(*cat_id, cat_names)
+            ));
+        }
+    }
+    lines
+}
+
+fn print_cat_pool(gatya_slot_data: &[Vec<i32>], cat_names: &HashMap<i32, String>) {
+    for line in get_cat_pool_lines(gatya_slot_data, cat_names).iter() {
+        println!("{}", line);
+    }
+}
+
+fn select_cats(gatya_slot_data: &[Vec<i32>], cat_names: &HashMap<i32, String>) -> Vec<i32> {
+    let pool: Vec<i32> = gatya_slot_data.concat();
+    print_cat_pool(gatya_slot_data, cat_names);
+    println!("Enter a cat ID, a name, or #number from the list above (? to show the list again)");
+
     let mut cats_ids: Vec<i32> = Vec::new();
     let mut counter: u32 = 0;
     loop {
-        let cat_id: i32 = get_int_from_user(
-            &format!(
-                "ID for cat {} (-1 to stop, {} for blank): ",
-                counter + 1,
-                BLANK_SLOT_USER,
-            ),
-            None,
-        );
+        let input: String = get_string_from_user(&format!(
+            "Cat {} (-1 to stop, {} for blank): ",
+            counter + 1,
+            BLANK_SLOT_USER,
+        ));
+        if input == "?" {
+            print_cat_pool(gatya_slot_data, cat_names);
+            continue;
+        }
+        let cat_id: i32 = if let Some(number) = input.strip_prefix('#') {
+            match number.trim().parse::<usize>() {
+                Ok(number) if number >= 1 && number <= pool.len() => pool[number - 1],
+                _ => {
+                    println!("Invalid number. Try again.");
+                    continue;
+                }
+            }
+        } else {
+            match input.parse::<i32>() {
+                Ok(cat_id) => cat_id,
+                Err(_) if input.is_empty() => {
+                    println!("Invalid input. Try again.");
+                    continue;
+                }
+                Err(_) => match select_cat_by_name(&input, cat_names, &pool) {
+                    Some(cat_id) => cat_id,
+                    None => continue,
+                },
+            }
+        };
         if cat_id == -1 {
             break;
         }
-        cats_ids.push(cat_id as i32);
+        if cat_id != BLANK_SLOT_USER && !pool.contains(&cat_id) {
+            println!("Cat {} is not in this banner. Try again.", cat_id);
+            continue;
+        }
+        println!("  {}", get_cat_entry_display(cat_id, cat_names));
+        cats_ids.push(cat_id);
         counter += 1;
     }
 
     if cats_ids.is_empty() {
         println!("No cats entered. Try again.");
-        return select_cats();
+        return select_cats(gatya_slot_data, cat_names);
     }
 
     cats_ids
 }
 
+// Same formats as select_cats, but any cat that can't be matched is an error
+fn parse_cats(
+    inputs: &[String],
+    gatya_slot_data: &[Vec<i32>],
+    cat_names: &HashMap<i32, String>,
+) -> error::Result<Vec<i32>> {
+    let pool: Vec<i32> = gatya_slot_data.concat();
+    let mut cats_ids: Vec<i32> = Vec::new();
+    for input in inputs.iter() {
+        let input: &str = input.trim();
+        let cat_id: i32 = if let Some(number) = input.strip_prefix('#') {
+            match number.trim().parse::<usize>() {
+                Ok(number) if number >= 1 && number <= pool.len() => pool[number - 1],
+                _ => {
+                    return Err(error::Error::InvalidInput(format!(
+                        "{} is not a number from the banner's pool (1-{})",
+                        input,
+                        pool.len()
+                    )))
+                }
+            }
+        } else if let Ok(cat_id) = input.parse::<i32>() {
+            cat_id
+        } else {
+            let matches: Vec<i32> = cat_names::find_cats_by_name(input, cat_names, &pool);
+            match matches.as_slice() {
+                [cat_id] => *cat_id,
+                [] => {
+                    return Err(error::Error::InvalidInput(format!(
+                        "no cat in this banner matches {:?}",
+                        input
+                    )))
+                }
+                _ => {
+                    let displays: Vec<String> = matches
+                        .iter()
+                        .map(|cat_id| cat_names::get_cat_display(*cat_id, cat_names))
+                        .collect();
+                    return Err(error::Error::InvalidInput(format!(
+                        "{:?} matches more than one cat: {}",
+                        input,
+                        displays.join(", ")
+                    )));
+                }
+            }
+        };
+        if cat_id != BLANK_SLOT_USER && !pool.contains(&cat_id) {
+            return Err(error::Error::MissingCat(cat_id));
+        }
+        cats_ids.push(cat_id);
+    }
+    if cats_ids.is_empty() {
+        return Err(error::Error::InvalidInput("no cats given".to_string()));
+    }
+    Ok(cats_ids)
+}
+
+fn get_cat_entry_display(cat_id: i32, cat_names: &HashMap<i32, String>) -> String {
+    if cat_id == BLANK_SLOT_USER {
+        return "Blank".to_string();
+    }
+    cat_names::get_cat_display(cat_id, cat_names)
+}
+
 fn select_rarities() -> Vec<i32> {
     let mut rarities: Vec<i32> = Vec::new();
     let mut counter: u32 = 0;
@@ -182,68 +530,441 @@ fn select_rarities() -> Vec<i32> {
     rarities
 }
 
-fn get_cat_slots(gatya_slot_data: Vec<Vec<i32>>, total_rares: u32) -> Vec<(u32, u32)> {
-    let cats_ids: Vec<i32> = select_cats();
+fn parse_rarities(inputs: &[String]) -> error::Result<Vec<i32>> {
+    let mut rarities: Vec<i32> = Vec::new();
+    for input in inputs.iter() {
+        let rarity: i32 = match input.trim().to_lowercase().as_str() {
+            "1" | "rare" => 0,
+            "2" | "super" | "super rare" => 1,
+            "3" | "uber" | "uber rare" => 2,
+            "4" | "legend" | "legend rare" => 3,
+            "-2" | "blank" => BLANK_SLOT as i32,
+            _ => {
+                return Err(error::Error::InvalidInput(format!(
+                    "unknown rarity {:?}",
+                    input
+                )))
+            }
+        };
+        rarities.push(rarity);
+    }
+    if rarities.is_empty() {
+        return Err(error::Error::InvalidInput("no rarities given".to_string()));
+    }
+    Ok(rarities)
+}
 
-    //let cats_ids: &[i32] = &[308, 50, 145, 37, 38, 35, 51, 308, 51, 150];
-    let cats: Vec<(u32, u32)> =
-        gatya_data::get_cat_list_from_ids(gatya_slot_data, cats_ids.to_vec());
+fn get_cat_slots(
+    gatya_slot_data: Vec<Vec<i32>>,
+    cats_ids: Vec<i32>,
+    total_rares: u32,
+) -> error::Result<Vec<(u32, u32)>> {
+    let cats: Vec<(u32, u32)> = gatya_data::get_cat_list_from_ids(gatya_slot_data, cats_ids)?;
 
     let collisions: bool = is_collisions(cats.clone(), total_rares);
 
     if collisions {
-        println!("WARNING: There might be a duplicate rare cat! The seed might not be found.")
+        eprintln!("WARNING: There might be a duplicate rare cat! The seed might not be found.")
     }
-    cats
+    Ok(cats)
 }
 
-#[tokio::main]
-async fn main() {
-    let cc: &str = &select_cc();
-    println!();
-    let (gatya_event, force) = select_event(cc).await;
-    let unitbuy_cat_data: Vec<Vec<i32>> = gatya_data::get_unitbuy_cat_data(cc, force).await;
+fn ask_if_eleven_draws() -> bool {
+    let input: i32 = get_int_from_user(
+        "Were the cats rolled with 11 draws? (1 for yes, 2 for no): ",
+        None,
+    );
+    match input {
+        1 => true,
+        2 => false,
+        _ => {
+            println!("Invalid input. Try again.");
+            ask_if_eleven_draws()
+        }
+    }
+}
 
-    let gatya_cat_data: Vec<Vec<i32>> = gatya_data::get_gatya_cat_data(cc, force).await;
+// Only step-up banners and guaranteed banners with ubers have more than one way to roll
+fn has_draw_choice(gatya_event: &gatya_data::GatyaEvent, total_uber_rares: u32) -> bool {
+    total_uber_rares > 0
+        && (gatya_event.flags.contains(gatya_data::GatyaFlags::STEP_UP) || gatya_event.guaranteed)
+}
 
-    let gatya_id: i32 = gatya_event.gatya_id.parse::<i32>().unwrap();
+// gatya.tsv has no step layout, so anything not given with --steps is an assumption
+fn get_step_up_steps(steps: &Option<String>) -> error::Result<Vec<simulator::Step>> {
+    match steps {
+        Some(steps) => simulator::parse_steps(steps).map_err(error::Error::InvalidInput),
+        None => Ok(get_assumed_steps()),
+    }
+}
 
-    let gatya_slot_data: Vec<Vec<i32>> =
-        gatya_data::get_gatya_slot_data(gatya_id, gatya_cat_data, unitbuy_cat_data);
+fn get_assumed_steps() -> Vec<simulator::Step> {
+    let steps: Vec<simulator::Step> = simulator::get_default_steps();
+    eprintln!(
+        "Warning: assuming the step-up steps are {}, if this banner's differ give its own (see --steps)",
+        simulator::format_steps(&steps)
+    );
+    steps
+}
 
-    let total_rares: u32 = gatya_slot_data[0].len() as u32;
-    let total_super_rares: u32 = gatya_slot_data[1].len() as u32;
-    let total_uber_rares: u32 = gatya_slot_data[2].len() as u32;
-    let total_legend_rares: u32 = gatya_slot_data[3].len() as u32;
+fn select_draw_mode(
+    gatya_event: &gatya_data::GatyaEvent,
+    total_uber_rares: u32,
+    steps: &Option<String>,
+) -> error::Result<simulator::DrawMode> {
+    if !has_draw_choice(gatya_event, total_uber_rares) {
+        return Ok(simulator::DrawMode::Single);
+    }
+    if gatya_event.flags.contains(gatya_data::GatyaFlags::STEP_UP) {
+        let steps: Vec<simulator::Step> = get_step_up_steps(steps)?;
+        for (i, step) in steps.iter().enumerate() {
+            let guaranteed: &str = if step.guaranteed {
+                ", guaranteed uber"
+            } else {
+                ""
+            };
+            println!(
+                "Step {}: {} rolls for {} cat food{}",
+                i + 1,
+                step.rolls,
+                step.price,
+                guaranteed
+            );
+        }
+        loop {
+            let input: i32 =
+                get_int_from_user("Step you were on for your first cat (default 1): ", Some(1));
+            if input < 1 || input > steps.len() as i32 {
+                println!("Invalid input. Try again.");
+                continue;
+            }
+            return Ok(simulator::DrawMode::StepUp(simulator::StepUp::new(
+                steps,
+                (input - 1) as usize,
+            )));
+        }
+    }
+    if ask_if_eleven_draws() {
+        return Ok(simulator::DrawMode::ElevenDraws);
+    }
+    Ok(simulator::DrawMode::Single)
+}
 
-    let legend_chance: u32 = 10000 - gatya_event.legend_rare_chance.parse::<u32>().unwrap();
-    let uber_chance: u32 = legend_chance - gatya_event.uber_rare_chance.parse::<u32>().unwrap();
-    let super_rare_chance: u32 =
-        uber_chance - gatya_event.super_rare_chance.parse::<u32>().unwrap();
+fn get_draw_mode(
+    args: &cli::DrawArgs,
+    gatya_event: &gatya_data::GatyaEvent,
+    total_uber_rares: u32,
+) -> error::Result<simulator::DrawMode> {
+    match args.draw {
+        None => select_draw_mode(gatya_event, total_uber_rares, &args.steps),
+        Some(cli::DrawArg::Single) => Ok(simulator::DrawMode::Single),
+        Some(cli::DrawArg::Eleven) => {
+            if !has_draw_choice(gatya_event, total_uber_rares) {
+                return Err(error::Error::InvalidInput(
+                    "11 draws need a guaranteed or step-up banner with uber rares".to_string(),
+                ));
+            }
+            Ok(simulator::DrawMode::ElevenDraws)
+        }
+        Some(cli::DrawArg::StepUp) => {
+            // the step-up guarantee rolls from the uber pool
+            if !has_draw_choice(gatya_event, total_uber_rares)
+                || !gatya_event.flags.contains(gatya_data::GatyaFlags::STEP_UP)
+            {
+                return Err(error::Error::InvalidInput(
+                    "step-up draws need a step-up banner with uber rares".to_string(),
+                ));
+            }
+            let steps: Vec<simulator::Step> = get_step_up_steps(&args.steps)?;
+            if args.step < 1 || args.step > steps.len() {
+                return Err(error::Error::InvalidInput(format!(
+                    "step {} is not between 1 and {}",
+                    args.step,
+                    steps.len()
+                )));
+            }
+            Ok(simulator::DrawMode::StepUp(simulator::StepUp::new(
+                steps,
+                args.step - 1,
+            )))
+        }
+    }
+}
 
-    println!();
+fn get_roll_display(
+    roll: &simulator::Roll,
+    position: usize,
+    cat_names: &HashMap<i32, String>,
+) -> String {
+    let guaranteed: &str = if roll.guaranteed { " (guaranteed)" } else { "" };
+    format!(
+        "{}. {} - {} (slot {}){}",
+        position,
+        simulator::get_rarity_name(roll.rarity),
+        cat_names::get_cat_display(roll.cat_id, cat_names),
+        roll.slot,
+        guaranteed
+    )
+}
 
-    let seek_or_find: i32 = get_int_from_user(
-        "1. Find seed by cats\n2. Seek seed by rarities\nEnter choice: ",
-        None,
+fn print_rolls(
+    rolls: &[simulator::Roll],
+    first_position: usize,
+    draw_mode: &simulator::DrawMode,
+    cat_names: &HashMap<i32, String>,
+) {
+    let mut last_step: Option<usize> = None;
+    for (i, roll) in rolls.iter().enumerate() {
+        if let (simulator::DrawMode::StepUp(step_up), Some(step)) = (draw_mode, roll.step) {
+            if last_step != Some(step) {
+                println!(
+                    "Step {} ({} cat food):",
+                    step + 1,
+                    step_up.steps[step].price
+                );
+                last_step = Some(step);
+            }
+        }
+        println!(
+            "{}",
+            get_roll_display(roll, first_position + i + 1, cat_names)
+        );
+    }
+}
+
+fn print_next_rolls(
+    seed: u32,
+    cats: &[(u32, u32)],
+    gatya_event: &gatya_data::GatyaEvent,
+    gatya_slot_data: &[Vec<i32>],
+    draw_mode: &simulator::DrawMode,
+    cat_names: &HashMap<i32, String>,
+) {
+    let end_seed: u32 = simulator::get_end_seed(seed, cats);
+    let (rolls, _) = simulator::simulate_rolls(
+        end_seed,
+        gatya_event,
+        gatya_slot_data,
+        cats.len(),
+        NEXT_ROLLS,
+        draw_mode,
     );
-    let mut cats: Vec<(u32, u32)> = Vec::new();
-    if seek_or_find == 1 {
-        cats = get_cat_slots(gatya_slot_data.clone(), total_rares);
+    println!("\nNext rolls (seed after your cats: {}):", end_seed);
+    print_rolls(&rolls, cats.len(), draw_mode, cat_names);
+}
+
+fn is_valid_cc(cc: &str) -> bool {
+    matches!(cc, "en" | "jp" | "kr" | "tw")
+}
+
+fn import(path: &str, cc: &str) -> error::Result<()> {
+    if !is_valid_cc(cc) {
+        return Err(error::Error::UnknownRegion(cc.to_string()));
+    }
+    let missing: Vec<String> = data_bundle::import_bundle(path, cc)?;
+    if !missing.is_empty() {
+        return Err(error::Error::MissingData(missing));
+    }
+    println!("All data imported, you can now use --offline");
+    Ok(())
+}
+
+async fn start_mock() -> error::Result<config::Config> {
+    let addr: SocketAddr = mock_server::start(0).await?;
+    let data_dir: PathBuf = std::env::temp_dir().join("bc-gatya-seeker-mock");
+    eprintln!("Using mock server at http://{}", addr);
+    Ok(mock_server::get_mock_config(addr, data_dir))
+}
+
+async fn serve_mock(port: u16) -> error::Result<()> {
+    let addr: SocketAddr = mock_server::start(port).await?;
+    println!("Mock server running at http://{}", addr);
+    println!("Point the seeker at it with:");
+    for env_var in [
+        "BC_SEEKER_BACKUPS_URL",
+        "BC_SEEKER_AUTH_URL",
+        "BC_SEEKER_EVENTS_URL",
+        "BC_SEEKER_BCDATA_URL",
+    ] {
+        println!("  export {}=http://{}", env_var, addr);
+    }
+    println!("Press Ctrl+C to stop");
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+async fn list_events(args: &cli::EventsArgs, offline: bool) -> error::Result<()> {
+    check_required_args(
+        args.output.format,
+        &[("--region", args.region.region.is_some())],
+    )?;
+    let cc: String = get_cc(&args.region)?;
+    let data: String = load_event_data(&cc, offline, args.update).await?;
+    let gatya_events: Vec<gatya_data::GatyaEvent> = if args.snapshots {
+        snapshots::get_all_events(&cc, data)?
     } else {
-        let rarities: Vec<i32> = select_rarities();
-        for rarity in rarities.iter() {
-            cats.push((*rarity as u32, IGNORE_SLOT));
+        gatya_data::parse_gatya_events(data)?
+    };
+    let gatya_events: Vec<gatya_data::GatyaEvent> = gatya_events
+        .into_iter()
+        .filter(|gatya_event| !gatya_event.banner_txt.is_empty())
+        .collect();
+    let filter: gatya_data::EventFilter = gatya_data::EventFilter {
+        status: args.status.map(|status| match status {
+            cli::StatusArg::Running => gatya_data::EventStatus::Running,
+            cli::StatusArg::Upcoming => gatya_data::EventStatus::Upcoming,
+            cli::StatusArg::Past => gatya_data::EventStatus::Past,
+        }),
+        text: args.text.clone(),
+        gatya_id: args.gatya,
+        sort: match args.sort {
+            cli::SortArg::File => gatya_data::EventSort::File,
+            cli::SortArg::Start => gatya_data::EventSort::Start,
+            cli::SortArg::End => gatya_data::EventSort::End,
+            cli::SortArg::Id => gatya_data::EventSort::GatyaId,
+        },
+    };
+    let now: NaiveDateTime = Local::now().naive_local();
+    let gatya_events: Vec<gatya_data::GatyaEvent> =
+        gatya_data::filter_events(gatya_events, &filter, now);
+    match args.output.format {
+        cli::OutputFormat::Json => output::write_events_json(std::io::stdout(), &gatya_events, now),
+        cli::OutputFormat::Csv => output::write_events_csv(std::io::stdout(), &gatya_events, now),
+        cli::OutputFormat::Text => {
+            if gatya_events.is_empty() {
+                println!("No events match the filter.");
+            }
+            print_events(&gatya_events);
+            Ok(())
         }
     }
-    let slice_cats: &[(u32, u32)] = cats.as_slice();
+}
 
-    let thread_count: i32 = get_int_from_user("Enter total threads to use (default 8):", Some(8));
+async fn update_data(args: &cli::RegionArgs, offline: bool) -> error::Result<()> {
+    if offline {
+        return Err(error::Error::InvalidInput(
+            "data can't be updated in offline mode".to_string(),
+        ));
+    }
+    let cc: String = get_cc(args)?;
+    std::fs::create_dir_all(cache::get_data_dir())?;
+    eprintln!("Getting event data...");
+    get_event_data(&cc, true).await?;
+    gatya_data::get_unitbuy_cat_data(&cc, true, false).await?;
+    for gatya_type in [
+        gatya_data::GatyaType::Rare,
+        gatya_data::GatyaType::Normal,
+        gatya_data::GatyaType::Event,
+    ] {
+        gatya_data::get_gatya_cat_data(&cc, gatya_type, true, false).await?;
+    }
+    println!("All data updated");
+    Ok(())
+}
+
+struct Banner {
+    gatya_event: gatya_data::GatyaEvent,
+    gatya_slot_data: Vec<Vec<i32>>,
+    cat_names: HashMap<i32, String>,
+}
+
+async fn load_banner(
+    cc: &str,
+    gatya_event: gatya_data::GatyaEvent,
+    offline: bool,
+    force: bool,
+) -> error::Result<Banner> {
+    if offline {
+        data_bundle::check_offline_dataset(cc, gatya_event.gatya_type)?;
+    }
+    let unitbuy_cat_data: Vec<gatya_data::UnitBuy> =
+        gatya_data::get_unitbuy_cat_data(cc, force, offline).await?;
+
+    let gatya_cat_data: Vec<Vec<i32>> =
+        gatya_data::get_gatya_cat_data(cc, gatya_event.gatya_type, force, offline).await?;
+
+    let gatya_id: i32 = gatya_event.gatya_id as i32;
+
+    let gatya_pool: gatya_data::GatyaPool = gatya_data::get_gatya_slot_data(
+        gatya_id,
+        gatya_event.gatya_type,
+        gatya_cat_data,
+        &unitbuy_cat_data,
+    )?;
+    if !gatya_pool.skipped.is_empty() {
+        let skipped: Vec<String> = gatya_pool
+            .skipped
+            .iter()
+            .map(|(cat_id, rarity)| format!("{} ({:?})", cat_id, rarity))
+            .collect();
+        eprintln!(
+            "Skipping units that can't be rolled as a gacha rarity: {}",
+            skipped.join(", ")
+        );
+    }
+    let gatya_slot_data: Vec<Vec<i32>> = gatya_pool.slots;
+    gatya_data::check_pool_sizes(&gatya_event, &gatya_slot_data)?;
+
+    let cat_names: HashMap<i32, String> = if offline {
+        cat_names::get_cached_cat_names(cc)
+    } else {
+        match cat_names::get_cat_names(cc, &gatya_slot_data.concat(), force).await {
+            Ok(cat_names) => cat_names,
+            Err(err) => {
+                eprintln!("Could not get cat names, only IDs will be shown. {}", err);
+                HashMap::new()
+            }
+        }
+    };
+
+    Ok(Banner {
+        gatya_event,
+        gatya_slot_data,
+        cat_names,
+    })
+}
+
+fn get_default_thread_count() -> u32 {
+    std::thread::available_parallelism()
+        .map(|count| count.get() as u32)
+        .unwrap_or(8)
+}
+
+// Marks the entries rolled at guaranteed positions for the draw mode
+fn get_search_pattern(
+    gatya_event: &gatya_data::GatyaEvent,
+    mut cats: Vec<(u32, u32)>,
+    draw_mode: &simulator::DrawMode,
+) -> Vec<(u32, u32)> {
+    for (position, cat) in cats.iter_mut().enumerate() {
+        let guaranteed: bool = draw_mode.is_guaranteed_position(gatya_event, position);
+        *cat = simulator::get_pattern_entry(cat.0, cat.1, guaranteed);
+    }
+    cats
+}
+
+// Returns the pattern that was searched for, the matching seeds and how long it took
+fn find_banner_seeds(
+    banner: &Banner,
+    cats: Vec<(u32, u32)>,
+    draw_mode: &simulator::DrawMode,
+    thread_count: u32,
+    progress: Arc<AtomicU64>,
+) -> (Vec<(u32, u32)>, Vec<u32>, std::time::Duration) {
+    let gatya_slot_data: &[Vec<i32>] = &banner.gatya_slot_data;
+    let total_rares: u32 = gatya_slot_data[0].len() as u32;
+    let total_super_rares: u32 = gatya_slot_data[1].len() as u32;
+    let total_uber_rares: u32 = gatya_slot_data[2].len() as u32;
+    let total_legend_rares: u32 = gatya_slot_data[3].len() as u32;
+
+    let (super_rare_chance, uber_chance, legend_chance) =
+        banner.gatya_event.get_rarity_thresholds();
+
+    let cats: Vec<(u32, u32)> = get_search_pattern(&banner.gatya_event, cats, draw_mode);
 
-    println!("\nFinding seed...");
     let start: Instant = Instant::now();
     let seeds: Vec<u32> = find_seed(
-        slice_cats,
+        &cats,
         total_rares,
         total_super_rares,
         total_uber_rares,
@@ -251,16 +972,71 @@ async fn main() {
         legend_chance,
         uber_chance,
         super_rare_chance,
-        thread_count.try_into().unwrap(),
+        thread_count.max(1),
+        progress,
+    );
+    (cats, seeds, start.elapsed())
+}
+
+fn search_seeds(
+    banner: &Banner,
+    cats: Vec<(u32, u32)>,
+    draw_mode: &simulator::DrawMode,
+    thread_count: u32,
+    format: cli::OutputFormat,
+) -> error::Result<()> {
+    let gatya_slot_data: &[Vec<i32>] = &banner.gatya_slot_data;
+    eprintln!("\nFinding seed...");
+    let (cats, seeds, duration) = find_banner_seeds(
+        banner,
+        cats,
+        draw_mode,
+        thread_count,
+        Arc::new(AtomicU64::new(0)),
     );
-    let duration: std::time::Duration = start.elapsed();
+    let slice_cats: &[(u32, u32)] = cats.as_slice();
+
+    let search: output::SeedSearch = output::SeedSearch {
+        gatya_event: &banner.gatya_event,
+        gatya_slot_data,
+        cat_names: &banner.cat_names,
+        cats: slice_cats,
+        draw_mode,
+        seeds: &seeds,
+        duration,
+    };
+    match format {
+        cli::OutputFormat::Json => {
+            return output::write_seeds_json(std::io::stdout(), &search, Local::now().naive_local())
+        }
+        cli::OutputFormat::Csv => return output::write_seeds_csv(std::io::stdout(), &search),
+        cli::OutputFormat::Text => {}
+    }
 
     println!();
+    print_seeds(banner, slice_cats, &seeds, draw_mode);
+    println!("\nTime taken to find seed: {:?}", duration);
+    Ok(())
+}
 
+fn print_seeds(
+    banner: &Banner,
+    cats: &[(u32, u32)],
+    seeds: &[u32],
+    draw_mode: &simulator::DrawMode,
+) {
     if seeds.is_empty() {
         println!("Seed not found. Try again.");
     } else if seeds.len() == 1 {
         println!("Seed: {}", seeds[0]);
+        print_next_rolls(
+            seeds[0],
+            cats,
+            &banner.gatya_event,
+            &banner.gatya_slot_data,
+            draw_mode,
+            &banner.cat_names,
+        );
     } else {
         println!("Multiple seeds found. You need to enter more cats!");
         println!("\nSeeds: ");
@@ -272,7 +1048,346 @@ async fn main() {
             println!("... and {} more", seeds.len() - max_seeds);
         }
     }
-    println!("\nTime taken to find seed: {:?}", duration);
+}
+
+// Prompts would mix with json or csv on stdout, so anything they would ask for has to be given
+fn check_required_args(format: cli::OutputFormat, args: &[(&str, bool)]) -> error::Result<()> {
+    if format == cli::OutputFormat::Text {
+        return Ok(());
+    }
+    let missing: Vec<&str> = args
+        .iter()
+        .filter(|(_, given)| !given)
+        .map(|(name, _)| *name)
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(error::Error::InvalidInput(format!(
+        "{} {} required with --format json or csv",
+        missing.join(", "),
+        if missing.len() == 1 { "is" } else { "are" }
+    )))
+}
+
+fn check_draw_arg(
+    args: &cli::DrawArgs,
+    banner: &Banner,
+    format: cli::OutputFormat,
+) -> error::Result<()> {
+    let total_uber_rares: u32 = banner.gatya_slot_data[2].len() as u32;
+    let given: bool =
+        args.draw.is_some() || !has_draw_choice(&banner.gatya_event, total_uber_rares);
+    check_required_args(format, &[("--draw", given)])
+}
+
+async fn find(args: &cli::FindArgs, offline: bool) -> error::Result<()> {
+    check_required_args(
+        args.output.format,
+        &[
+            ("--region", args.event.region.region.is_some()),
+            ("--gatya", args.event.gatya.is_some()),
+            ("--cats", args.cats.is_some()),
+        ],
+    )?;
+    let cc: String = get_cc(&args.event.region)?;
+    let gatya_event: gatya_data::GatyaEvent = get_event(&cc, &args.event, offline).await?;
+    let banner: Banner = load_banner(&cc, gatya_event, offline, args.event.update).await?;
+    check_draw_arg(&args.draw, &banner, args.output.format)?;
+    let cats_ids: Vec<i32> = match &args.cats {
+        Some(cats) => parse_cats(cats, &banner.gatya_slot_data, &banner.cat_names)?,
+        None => {
+            println!();
+            select_cats(&banner.gatya_slot_data, &banner.cat_names)
+        }
+    };
+    let total_rares: u32 = banner.gatya_slot_data[0].len() as u32;
+    let cats: Vec<(u32, u32)> =
+        get_cat_slots(banner.gatya_slot_data.clone(), cats_ids, total_rares)?;
+    let draw_mode: simulator::DrawMode = get_draw_mode(
+        &args.draw,
+        &banner.gatya_event,
+        banner.gatya_slot_data[2].len() as u32,
+    )?;
+    let thread_count: u32 = args.threads.unwrap_or_else(get_default_thread_count);
+    search_seeds(&banner, cats, &draw_mode, thread_count, args.output.format)
+}
+
+async fn seek(args: &cli::SeekArgs, offline: bool) -> error::Result<()> {
+    check_required_args(
+        args.output.format,
+        &[
+            ("--region", args.event.region.region.is_some()),
+            ("--gatya", args.event.gatya.is_some()),
+            ("--rarities", args.rarities.is_some()),
+        ],
+    )?;
+    let cc: String = get_cc(&args.event.region)?;
+    let gatya_event: gatya_data::GatyaEvent = get_event(&cc, &args.event, offline).await?;
+    let banner: Banner = load_banner(&cc, gatya_event, offline, args.event.update).await?;
+    check_draw_arg(&args.draw, &banner, args.output.format)?;
+    let rarities: Vec<i32> = match &args.rarities {
+        Some(rarities) => parse_rarities(rarities)?,
+        None => {
+            println!();
+            select_rarities()
+        }
+    };
+    let cats: Vec<(u32, u32)> = rarities
+        .iter()
+        .map(|rarity| (*rarity as u32, IGNORE_SLOT))
+        .collect();
+    let draw_mode: simulator::DrawMode = get_draw_mode(
+        &args.draw,
+        &banner.gatya_event,
+        banner.gatya_slot_data[2].len() as u32,
+    )?;
+    let thread_count: u32 = args.threads.unwrap_or_else(get_default_thread_count);
+    search_seeds(&banner, cats, &draw_mode, thread_count, args.output.format)
+}
+
+fn ask_for_seed() -> u32 {
+    loop {
+        let input: String = get_string_from_user("Enter seed: ");
+        match input.parse::<u32>() {
+            Ok(seed) => return seed,
+            Err(_) => println!("Invalid input. Try again."),
+        }
+    }
+}
+
+async fn simulate(args: &cli::SimulateArgs, offline: bool) -> error::Result<()> {
+    let cc: String = get_cc(&args.event.region)?;
+    let gatya_event: gatya_data::GatyaEvent = get_event(&cc, &args.event, offline).await?;
+    let banner: Banner = load_banner(&cc, gatya_event, offline, args.event.update).await?;
+    let seed: u32 = match args.seed {
+        Some(seed) => seed,
+        None => ask_for_seed(),
+    };
+    let draw_mode: simulator::DrawMode = get_draw_mode(
+        &args.draw,
+        &banner.gatya_event,
+        banner.gatya_slot_data[2].len() as u32,
+    )?;
+    let (rolls, end_seed) = simulator::simulate_rolls(
+        seed,
+        &banner.gatya_event,
+        &banner.gatya_slot_data,
+        0,
+        args.count,
+        &draw_mode,
+    );
+    println!("\nRolls from seed {}:", seed);
+    print_rolls(&rolls, 0, &draw_mode, &banner.cat_names);
+    println!("\nSeed after these rolls: {}", end_seed);
+    Ok(())
+}
+
+// Finds the banner a pattern file names by gatya ID, banner text or both
+async fn get_pattern_event(
+    pattern: &pattern_file::PatternFile,
+    offline: bool,
+) -> error::Result<gatya_data::GatyaEvent> {
+    let data: String = load_event_data(&pattern.region, offline, false).await?;
+    let filter: gatya_data::EventFilter = gatya_data::EventFilter {
+        text: pattern.banner.clone(),
+        gatya_id: pattern.gatya_id,
+        ..Default::default()
+    };
+    let gatya_events: Vec<gatya_data::GatyaEvent> = gatya_data::filter_events(
+        gatya_data::parse_gatya_events(data)?,
+        &filter,
+        Local::now().naive_local(),
+    );
+    let gatya_event: gatya_data::GatyaEvent = match gatya_events.first() {
+        Some(gatya_event) => gatya_event.clone(),
+        None => {
+            return Err(error::Error::InvalidInput(
+                "no event matches the pattern's gatya_id and banner".to_string(),
+            ))
+        }
+    };
+    // reruns of a banner share its text, so text alone has to pick out one banner
+    if pattern.banner.is_some() {
+        let mut banners: Vec<String> = gatya_events
+            .iter()
+            .map(|gatya_event| format!("{} (id {})", gatya_event.banner_txt, gatya_event.gatya_id))
+            .collect();
+        banners.sort();
+        banners.dedup();
+        if banners.len() > 1 {
+            return Err(error::Error::InvalidInput(format!(
+                "the banner text matches more than one banner, add a gatya_id: {}",
+                banners.join(", ")
+            )));
+        }
+    }
+    eprintln!("Selected event: {}", gatya_event.banner_txt);
+    Ok(gatya_event)
+}
+
+// Runs one pattern file without prompting and writes its result, returning the result path and seed count
+// Like --draw, "draw" can only be left out when the banner has no other way to roll
+fn get_pattern_draw_mode(
+    pattern: &pattern_file::PatternFile,
+    banner: &Banner,
+) -> error::Result<simulator::DrawMode> {
+    let total_uber_rares: u32 = banner.gatya_slot_data[2].len() as u32;
+    if pattern.draw.is_none() && has_draw_choice(&banner.gatya_event, total_uber_rares) {
+        return Err(error::Error::InvalidInput(
+            "the banner is guaranteed or a step-up, set \"draw\" to say how the cats were rolled"
+                .to_string(),
+        ));
+    }
+    let draw_args: cli::DrawArgs = cli::DrawArgs {
+        draw: Some(pattern.draw.unwrap_or(cli::DrawArg::Single)),
+        step: pattern.step,
+        steps: pattern.steps.clone(),
+    };
+    get_draw_mode(&draw_args, &banner.gatya_event, total_uber_rares)
+}
+
+async fn run_pattern_file(
+    path: &Path,
+    args: &cli::BatchArgs,
+    offline: bool,
+) -> error::Result<(PathBuf, usize)> {
+    let pattern: pattern_file::PatternFile = pattern_file::read_pattern_file(path)?;
+    if !is_valid_cc(&pattern.region) {
+        return Err(error::Error::UnknownRegion(pattern.region));
+    }
+    let gatya_event: gatya_data::GatyaEvent = get_pattern_event(&pattern, offline).await?;
+    let banner: Banner = load_banner(&pattern.region, gatya_event, offline, false).await?;
+
+    let cats: Vec<(u32, u32)> = match &pattern.cats {
+        Some(cats) => {
+            let cats_ids: Vec<i32> = parse_cats(cats, &banner.gatya_slot_data, &banner.cat_names)?;
+            let total_rares: u32 = banner.gatya_slot_data[0].len() as u32;
+            get_cat_slots(banner.gatya_slot_data.clone(), cats_ids, total_rares)?
+        }
+        None => parse_rarities(pattern.rarities.as_deref().unwrap_or_default())?
+            .iter()
+            .map(|rarity| (*rarity as u32, IGNORE_SLOT))
+            .collect(),
+    };
+
+    let draw_mode: simulator::DrawMode = get_pattern_draw_mode(&pattern, &banner)?;
+
+    let thread_count: u32 = pattern.threads.unwrap_or_else(get_default_thread_count);
+    eprintln!("\nFinding seed...");
+    let (cats, seeds, duration) = find_banner_seeds(
+        &banner,
+        cats,
+        &draw_mode,
+        thread_count,
+        Arc::new(AtomicU64::new(0)),
+    );
+    let search: output::SeedSearch = output::SeedSearch {
+        gatya_event: &banner.gatya_event,
+        gatya_slot_data: &banner.gatya_slot_data,
+        cat_names: &banner.cat_names,
+        cats: &cats,
+        draw_mode: &draw_mode,
+        seeds: &seeds,
+        duration,
+    };
+
+    let extension: &str = match args.format {
+        cli::BatchFormat::Json => "json",
+        cli::BatchFormat::Csv => "csv",
+    };
+    let result_path: PathBuf =
+        pattern_file::get_result_path(path, args.out_dir.as_deref(), extension);
+    let file: std::fs::File = std::fs::File::create(&result_path)?;
+    match args.format {
+        cli::BatchFormat::Json => {
+            output::write_seeds_json(file, &search, Local::now().naive_local())?
+        }
+        cli::BatchFormat::Csv => output::write_seeds_csv(file, &search)?,
+    }
+    Ok((result_path, seeds.len()))
+}
+
+// Keeps going when a pattern file fails, and reports how many did at the end
+async fn run_batch(args: &cli::BatchArgs, offline: bool) -> error::Result<()> {
+    let pattern_paths: Vec<PathBuf> = pattern_file::get_pattern_paths(&args.paths)?;
+    if pattern_paths.is_empty() {
+        return Err(error::Error::InvalidInput(
+            "no pattern files found".to_string(),
+        ));
+    }
+    if let Some(out_dir) = &args.out_dir {
+        std::fs::create_dir_all(out_dir)?;
+    }
+    let mut failed: usize = 0;
+    for pattern_path in pattern_paths.iter() {
+        match run_pattern_file(pattern_path, args, offline).await {
+            Ok((result_path, seed_count)) => println!(
+                "{}: seeds found: {}, written to {}",
+                pattern_path.display(),
+                seed_count,
+                result_path.display()
+            ),
+            Err(err) => {
+                failed += 1;
+                println!("{}: {}", pattern_path.display(), err);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(error::Error::InvalidInput(format!(
+            "{} of {} pattern files failed",
+            failed,
+            pattern_paths.len()
+        )));
+    }
+    Ok(())
+}
+
+async fn run_command(cli: cli::Cli) -> error::Result<()> {
+    let is_mock_server: bool = matches!(cli.command, Some(cli::Command::MockServer { .. }));
+    let mut config: config::Config = if cli.mock && !is_mock_server {
+        start_mock().await?
+    } else {
+        config::load_config()
+    };
+    if let Some(client_version) = &cli.client_version {
+        config.client_version = Some(client_version.clone());
+    }
+    if cli.skip_signature_check {
+        config.verify_signatures = false;
+    }
+    config::set(config);
+    match cli.command {
+        None => repl::run_repl(cli.offline).await,
+        Some(cli::Command::Events(args)) => list_events(&args, cli.offline).await,
+        Some(cli::Command::Find(args)) => find(&args, cli.offline).await,
+        Some(cli::Command::Seek(args)) => seek(&args, cli.offline).await,
+        Some(cli::Command::Simulate(args)) => simulate(&args, cli.offline).await,
+        Some(cli::Command::Batch(args)) => run_batch(&args, cli.offline).await,
+        Some(cli::Command::Tui(args)) => tui::run_tui(&args, cli.offline).await,
+        Some(cli::Command::UpdateData(args)) => update_data(&args, cli.offline).await,
+        Some(cli::Command::Import { path, cc }) => import(&path, &cc),
+        Some(cli::Command::MockServer { port }) => serve_mock(port).await,
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli: cli::Cli = cli::Cli::parse();
+    let result: error::Result<()> = run_command(cli).await;
+    if let Err(err) = result {
+        eprintln!("\n{}", err);
+        match err {
+            error::Error::Network(_) => eprintln!("Check your internet connection and try again."),
+            error::Error::Http { status: 500.., .. } => {
+                eprintln!("The server is having problems, try again later.")
+            }
+            error::Error::Parse(_) => eprintln!("Try updating the game data."),
+            _ => {}
+        }
+        std::process::exit(1);
+    }
 }
 
 fn is_collisions(cats: Vec<(u32, u32)>, total_rares: u32) -> bool {
@@ -296,6 +1411,7 @@ fn is_collisions(cats: Vec<(u32, u32)>, total_rares: u32) -> bool {
     false
 }
 
+#[allow(clippy::too_many_arguments)]
 fn find_seed(
     cats: &[(u32, u32)],
     total_rares: u32,
@@ -306,6 +1422,7 @@ fn find_seed(
     uber_chance: u32,
     super_rare_chance: u32,
     total_threads: u32,
+    progress: Arc<AtomicU64>,
 ) -> Vec<u32> {
     let mut threads: Vec<std::thread::JoinHandle<Vec<u32>>> = Vec::new();
     let mut start_point: u32 = 1;
@@ -314,6 +1431,7 @@ fn find_seed(
     let mut end_point: u32 = step;
     for i in 0..total_threads {
         let cats: Vec<(u32, u32)> = cats.to_vec();
+        let progress: Arc<AtomicU64> = progress.clone();
         threads.push(std::thread::spawn(move || {
             find_seed_range(
                 &cats,
@@ -326,6 +1444,7 @@ fn find_seed(
                 super_rare_chance,
                 start_point,
                 end_point,
+                &progress,
             )
         }));
         if i == total_threads - 1 {
@@ -344,8 +1463,13 @@ fn find_seed(
 const MODULUS: u32 = 10000;
 const BLANK_SLOT: u32 = 20;
 const IGNORE_SLOT: u32 = 21;
+const GUARANTEED_UBER: u32 = 22;
+const NEXT_ROLLS: usize = 10;
 const BLANK_SLOT_USER: i32 = -2;
+// Seeds checked between progress updates, the search covers every u32 seed
+const PROGRESS_STEP: u32 = 1 << 20;
 
+#[allow(clippy::too_many_arguments)]
 fn find_seed_range(
     cats: &[(u32, u32)],
     total_rares: u32,
@@ -357,6 +1481,7 @@ fn find_seed_range(
     super_rare_chance: u32,
     start_point: u32,
     end_point: u32,
+    progress: &AtomicU64,
 ) -> Vec<u32> {
     let last_cat: usize = cats.len() - 1;
 
@@ -373,11 +1498,26 @@ fn find_seed_range(
     let mut seeds: Vec<u32> = Vec::new();
 
     for i in start_point..end_point {
+        if i % PROGRESS_STEP == 0 {
+            progress.fetch_add(PROGRESS_STEP as u64, Ordering::Relaxed);
+        }
         seed = i;
         for (j, cat) in cats.iter().enumerate() {
             seed ^= seed << 13;
             seed ^= seed >> 17;
             seed ^= seed << 15;
+
+            // guaranteed ubers skip the rarity roll
+            if cat.0 == GUARANTEED_UBER {
+                slot = seed % total_uber_rares;
+                if slot != cat.1 && cat.1 != IGNORE_SLOT {
+                    break;
+                }
+                if j == last_cat {
+                    seeds.push(i);
+                }
+                continue;
+            }
             prob = seed % MODULUS;
 
             if cat.0 != BLANK_SLOT {
@@ -420,3 +1560,54 @@ fn find_seed_range(
     }
     seeds
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gatya_data::GatyaFlags;
+    use crate::mock_server::tests::{get_test_event, get_test_slots};
+
+    fn get_test_banner(guaranteed: bool, flags: GatyaFlags) -> Banner {
+        Banner {
+            gatya_event: get_test_event(guaranteed, flags),
+            gatya_slot_data: get_test_slots(),
+            cat_names: HashMap::new(),
+        }
+    }
+
+    fn get_pattern(draw: Option<cli::DrawArg>) -> pattern_file::PatternFile {
+        pattern_file::PatternFile {
+            region: "en".to_string(),
+            gatya_id: Some(1),
+            banner: None,
+            cats: Some(vec!["4".to_string()]),
+            rarities: None,
+            draw,
+            step: 1,
+            steps: None,
+            threads: None,
+        }
+    }
+
+    #[test]
+    fn pattern_files_need_a_draw_when_the_banner_has_a_choice() {
+        let banner: Banner = get_test_banner(false, GatyaFlags::empty());
+        let draw_mode: simulator::DrawMode =
+            get_pattern_draw_mode(&get_pattern(None), &banner).unwrap();
+        assert!(matches!(draw_mode, simulator::DrawMode::Single));
+
+        for banner in [
+            get_test_banner(true, GatyaFlags::empty()),
+            get_test_banner(false, GatyaFlags::STEP_UP),
+        ] {
+            assert!(matches!(
+                get_pattern_draw_mode(&get_pattern(None), &banner),
+                Err(error::Error::InvalidInput(_))
+            ));
+        }
+        let banner: Banner = get_test_banner(true, GatyaFlags::empty());
+        let draw_mode: simulator::DrawMode =
+            get_pattern_draw_mode(&get_pattern(Some(cli::DrawArg::Eleven)), &banner).unwrap();
+        assert!(matches!(draw_mode, simulator::DrawMode::ElevenDraws));
+    }
+}
@@ -0,0 +1,386 @@
+use std::net::SocketAddr;
+
+use base64::Engine;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::config::Config;
+use crate::error;
+use crate::event_data;
+
+pub const MOCK_GAME_VERSION: &str = "13.0.0";
+pub const MOCK_ACCOUNT_ID: &str = "mockaccount1234";
+pub const MOCK_PASSWORD: &str = "mockpassword";
+pub const MOCK_UNIT_COUNT: i32 = 16;
+
+// The version latest.txt reports, which tests change to act like a game update
+static GAME_VERSION: std::sync::Mutex<&str> = std::sync::Mutex::new(MOCK_GAME_VERSION);
+
+#[cfg(test)]
+pub fn set_game_version(version: &'static str) {
+    *GAME_VERSION.lock().unwrap() = version;
+}
+
+const MOCK_GATYA_TSV: &str = "[start]\n\
+20200101\t1100\t20300101\t1100\t0\t999999\t0\t0\t1\t1\t1\t0\t0\t0\t0\t0\t7000\t0\t2500\t0\t470\t0\t30\t0\tMock Banner\n\
+20200101\t1100\t20300101\t1100\t0\t999999\t0\t0\t1\t1\t1\t0\t0\t4\t0\t0\t7000\t0\t2500\t0\t470\t1\t30\t0\tMock Step-Up Banner\n\
+20200101\t1100\t20300101\t1100\t0\t999999\t0\t0\t4\t1\t1\t0\t0\t0\t0\t0\t7000\t0\t2500\t0\t500\t0\t0\t0\tMock Event Capsule\n\
+[end]\n";
+
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+    signature: Option<String>,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: String) -> Response {
+        Response {
+            status: 200,
+            content_type,
+            body,
+            signature: None,
+        }
+    }
+
+    // Signs the body the same way the real auth and event servers do
+    fn signed(content_type: &'static str, body: String) -> Response {
+        let signature: String =
+            event_data::generate_signature(MOCK_ACCOUNT_ID.to_string(), body.clone());
+        Response {
+            signature: Some(signature),
+            ..Response::ok(content_type, body)
+        }
+    }
+
+    fn not_found() -> Response {
+        Response {
+            status: 404,
+            content_type: "text/plain",
+            body: "Not Found".to_string(),
+            signature: None,
+        }
+    }
+
+    fn bad_request(message: &str) -> Response {
+        Response {
+            status: 400,
+            content_type: "application/json",
+            body: json!({"statusCode": -1, "message": message}).to_string(),
+            signature: None,
+        }
+    }
+}
+
+fn get_reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        _ => "Not Found",
+    }
+}
+
+fn get_mock_token() -> String {
+    let encoder = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    let header: String = encoder.encode(json!({"alg": "none"}).to_string());
+    let payload: String = encoder.encode(json!({"exp": 4102444800_i64}).to_string());
+    format!("{}.{}.mock", header, payload)
+}
+
+fn get_mock_gatya_data() -> String {
+    let mut data: String = "0,1,2,3,-1\n".to_string();
+    let cat_ids: Vec<String> = (0..MOCK_UNIT_COUNT)
+        .map(|cat_id| cat_id.to_string())
+        .collect();
+    data.push_str(&cat_ids.join(","));
+    data.push_str(",-1\n");
+    data
+}
+
+// Rarity is column 13, cycling through rare, super rare, uber and legend
+fn get_mock_unitbuy_data() -> String {
+    let mut data: String = String::new();
+    for cat_id in 0..MOCK_UNIT_COUNT {
+        let mut row: Vec<String> = vec!["0".to_string(); 20];
+        row[13] = (2 + cat_id % 4).to_string();
+        data.push_str(&row.join(","));
+        data.push('\n');
+    }
+    data
+}
+
+fn route(request: &Request) -> Response {
+    let path: &str = request.path.split('?').next().unwrap_or("");
+    match (request.method.as_str(), path) {
+        ("GET", "/") if request.path.contains("action=createAccount") => Response::ok(
+            "application/json",
+            json!({"accountId": MOCK_ACCOUNT_ID}).to_string(),
+        ),
+        ("POST", "/v1/users") => Response::signed(
+            "application/json",
+            json!({"statusCode": 1, "payload": {"password": MOCK_PASSWORD}}).to_string(),
+        ),
+        ("POST", "/v1/tokens") => {
+            // only the mock account can log in, like a real account whose password is known
+            let json: serde_json::Value =
+                serde_json::from_str(&request.body).unwrap_or(serde_json::Value::Null);
+            if json["accountCode"] != MOCK_ACCOUNT_ID || json["password"] != MOCK_PASSWORD {
+                return Response::bad_request("invalid account code or password");
+            }
+            Response::signed(
+                "application/json",
+                json!({"statusCode": 1, "payload": {"token": get_mock_token()}}).to_string(),
+            )
+        }
+        ("GET", "/latest.txt") => Response::ok(
+            "text/plain",
+            format!("{0}en\n{0}jp\n{0}kr\n{0}tw\n", GAME_VERSION.lock().unwrap()),
+        ),
+        ("GET", path) if path.ends_with("_production/gatya.tsv") => {
+            Response::signed("text/plain", MOCK_GATYA_TSV.to_string())
+        }
+        ("GET", path) if path.contains("/DataLocal/GatyaDataSet") => {
+            Response::ok("text/plain", get_mock_gatya_data())
+        }
+        ("GET", path) if path.ends_with("/DataLocal/unitbuy.csv") => {
+            Response::ok("text/plain", get_mock_unitbuy_data())
+        }
+        ("GET", path) if path.contains("/resLocal/Unit_Explanation") => {
+            let file_name: &str = path.rsplit('/').next().unwrap_or("");
+            let unit: Option<i32> = file_name
+                .strip_prefix("Unit_Explanation")
+                .and_then(|rest| rest.split('_').next())
+                .and_then(|unit| unit.parse::<i32>().ok());
+            match unit {
+                Some(unit) if (1..=MOCK_UNIT_COUNT).contains(&unit) => Response::ok(
+                    "text/plain",
+                    format!("Mock Cat {}|A cat from the mock server\n", unit - 1),
+                ),
+                _ => Response::not_found(),
+            }
+        }
+        _ => Response::not_found(),
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<Request> {
+    let mut data: Vec<u8> = Vec::new();
+    let mut buffer: [u8; 4096] = [0; 4096];
+    let header_end: usize = loop {
+        let read: usize = stream.read(&mut buffer).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        data.extend_from_slice(&buffer[..read]);
+        if let Some(pos) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head: String = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method: String = request_line.next()?.to_string();
+    let path: String = request_line.next()?.to_string();
+
+    let content_length: usize = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0);
+    while data.len() - header_end < content_length {
+        let read: usize = stream.read(&mut buffer).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        data.extend_from_slice(&buffer[..read]);
+    }
+    let body: String = String::from_utf8_lossy(&data[header_end..]).to_string();
+    Some(Request { method, path, body })
+}
+
+async fn handle_connection(mut stream: TcpStream) {
+    let request: Request = match read_request(&mut stream).await {
+        Some(request) => request,
+        None => return,
+    };
+    let response: Response = route(&request);
+    let signature_header: String = match &response.signature {
+        Some(signature) => format!("Nyanko-Signature: {}\r\n", signature),
+        None => String::new(),
+    };
+    let reply: String = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        response.status,
+        get_reason(response.status),
+        response.content_type,
+        response.body.len(),
+        signature_header,
+        response.body
+    );
+    let _ = stream.write_all(reply.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+// Serves the account, auth, event and BCData endpoints until the runtime shuts down
+pub async fn start(port: u16) -> error::Result<SocketAddr> {
+    let listener: TcpListener = TcpListener::bind(("127.0.0.1", port)).await?;
+    let addr: SocketAddr = listener.local_addr()?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle_connection(stream));
+        }
+    });
+    Ok(addr)
+}
+
+pub fn get_mock_config(addr: SocketAddr, data_dir: std::path::PathBuf) -> Config {
+    let base_url: String = format!("http://{}", addr);
+    Config {
+        backups_url: base_url.clone(),
+        auth_url: base_url.clone(),
+        events_url: base_url.clone(),
+        bcdata_url: base_url,
+        data_dir: Some(data_dir),
+        client_version: None,
+        verify_signatures: true,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::path::PathBuf;
+    use std::sync::OnceLock;
+
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    use super::*;
+    use crate::account::{self, Account};
+    use crate::config;
+    use crate::gatya_data::{GatyaEvent, GatyaFlags, GatyaType};
+
+    // The mock server's banner (gatya id 1), for tests that don't need the server itself.
+    // Seed 123456789 rolls cats 4, 13, 12, 4, ... on it with single draws
+    pub fn get_test_event(guaranteed: bool, flags: GatyaFlags) -> GatyaEvent {
+        let date: NaiveDateTime = NaiveDate::from_ymd_opt(2020, 1, 1)
+            .unwrap()
+            .and_hms_opt(11, 0, 0)
+            .unwrap();
+        GatyaEvent {
+            start: date,
+            end: date,
+            gatya_type: GatyaType::Rare,
+            gatya_id: 1,
+            rare_chance: 7000,
+            super_rare_chance: 2500,
+            uber_rare_chance: 470,
+            legend_rare_chance: 30,
+            price: 0,
+            flags,
+            guaranteed,
+            banner_txt: "Mock Banner".to_string(),
+        }
+    }
+
+    // Its pool cycles through the rarities by cat ID, like the mock unitbuy.csv
+    pub fn get_test_slots() -> Vec<Vec<i32>> {
+        (0..4)
+            .map(|rarity| (0..4).map(|i| rarity + i * 4).collect())
+            .collect()
+    }
+
+    static TEST_SERVER: OnceLock<SocketAddr> = OnceLock::new();
+    // Tests that use the server share its account file, so they take turns
+    pub static TEST_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    // The config can only be set once, so every test shares one server, running on its own
+    // thread so it outlives each test's runtime
+    pub fn start_test_server() -> SocketAddr {
+        *TEST_SERVER.get_or_init(|| {
+            let (sender, receiver) = std::sync::mpsc::channel::<SocketAddr>();
+            std::thread::spawn(move || {
+                let runtime = tokio::runtime::Runtime::new().unwrap();
+                runtime.block_on(async {
+                    sender.send(start(0).await.unwrap()).unwrap();
+                    std::future::pending::<()>().await;
+                });
+            });
+            let addr: SocketAddr = receiver.recv().unwrap();
+            let data_dir: PathBuf = tempfile::tempdir().unwrap().into_path();
+            config::set(get_mock_config(addr, data_dir));
+            assert_eq!(
+                config::get().auth_url,
+                format!("http://{}", addr),
+                "the config was read before the test server started"
+            );
+            addr
+        })
+    }
+
+    fn remove_account() {
+        let _ = std::fs::remove_file(
+            config::get()
+                .data_dir
+                .as_ref()
+                .unwrap()
+                .join("account.json"),
+        );
+    }
+
+    #[tokio::test]
+    async fn creates_an_account_and_gets_event_data() {
+        start_test_server();
+        let _lock = TEST_LOCK.lock().await;
+        remove_account();
+
+        let data: String = event_data::get_event_data("en").await.unwrap();
+        assert!(data.contains("Mock Banner"));
+        let account: Account = account::load_account().unwrap();
+        assert_eq!(account.inquiry_code, MOCK_ACCOUNT_ID);
+        assert_eq!(account.password, MOCK_PASSWORD);
+        assert_eq!(account.tokens["en"], get_mock_token());
+
+        // the saved token is used the second time
+        let data: String = event_data::get_event_data("en").await.unwrap();
+        assert!(data.contains("Mock Step-Up Banner"));
+    }
+
+    #[tokio::test]
+    async fn replaces_a_rejected_account() {
+        start_test_server();
+        let _lock = TEST_LOCK.lock().await;
+        account::save_account(&Account {
+            inquiry_code: MOCK_ACCOUNT_ID.to_string(),
+            password: "stalepassword".to_string(),
+            tokens: std::collections::HashMap::new(),
+        })
+        .unwrap();
+
+        let data: String = event_data::get_event_data("jp").await.unwrap();
+        assert!(data.contains("Mock Event Capsule"));
+        let account: Account = account::load_account().unwrap();
+        assert_eq!(account.password, MOCK_PASSWORD);
+        assert_eq!(account.tokens["jp"], get_mock_token());
+    }
+
+    #[tokio::test]
+    async fn rejects_a_wrong_password() {
+        let addr: SocketAddr = start_test_server();
+        let body: String = json!({"accountCode": MOCK_ACCOUNT_ID, "password": "wrong"}).to_string();
+        let request = crate::http::get_client()
+            .post(format!("http://{}/v1/tokens", addr))
+            .body(body);
+        match crate::http::get_text(request).await {
+            Err(error::Error::Http { status, .. }) => assert_eq!(status, 400),
+            other => panic!("expected a 400 response, got {:?}", other.map(|_| ())),
+        }
+    }
+}
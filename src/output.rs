@@ -0,0 +1,343 @@
+use chrono::NaiveDateTime;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::error;
+use crate::gatya_data::{EventStatus, GatyaEvent, GatyaType};
+use crate::simulator::{self, DrawMode};
+use crate::{BLANK_SLOT, GUARANTEED_UBER, IGNORE_SLOT};
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+fn get_type_name(gatya_type: GatyaType) -> String {
+    match gatya_type {
+        GatyaType::Normal => "normal".to_string(),
+        GatyaType::Rare => "rare".to_string(),
+        GatyaType::Event => "event".to_string(),
+        GatyaType::Other(code) => code.to_string(),
+    }
+}
+
+fn get_status_name(status: EventStatus) -> &'static str {
+    match status {
+        EventStatus::Running => "running",
+        EventStatus::Upcoming => "upcoming",
+        EventStatus::Past => "past",
+    }
+}
+
+fn get_draw_mode_json(draw_mode: &DrawMode) -> serde_json::Value {
+    match draw_mode {
+        DrawMode::Single => json!({"mode": "single"}),
+        DrawMode::ElevenDraws => json!({"mode": "eleven"}),
+        DrawMode::StepUp(step_up) => json!({
+            "mode": "step-up",
+            "step": step_up.current_step + 1,
+            "steps": simulator::format_steps(&step_up.steps),
+        }),
+    }
+}
+
+pub fn get_event_json(gatya_event: &GatyaEvent, now: NaiveDateTime) -> serde_json::Value {
+    json!({
+        "gatya_id": gatya_event.gatya_id,
+        "type": get_type_name(gatya_event.gatya_type),
+        "banner": gatya_event.banner_txt,
+        "start": gatya_event.start.format(DATE_FORMAT).to_string(),
+        "end": gatya_event.end.format(DATE_FORMAT).to_string(),
+        "status": get_status_name(gatya_event.get_status(now)),
+        "price": gatya_event.price,
+        "rare_chance": gatya_event.rare_chance,
+        "super_rare_chance": gatya_event.super_rare_chance,
+        "uber_rare_chance": gatya_event.uber_rare_chance,
+        "legend_rare_chance": gatya_event.legend_rare_chance,
+        "guaranteed": gatya_event.guaranteed,
+        "flags": gatya_event.flags.bits(),
+        "tags": gatya_event.get_tags(),
+    })
+}
+
+pub fn write_events_json<W: Write>(
+    mut writer: W,
+    gatya_events: &[GatyaEvent],
+    now: NaiveDateTime,
+) -> error::Result<()> {
+    let events: Vec<serde_json::Value> = gatya_events
+        .iter()
+        .map(|gatya_event| get_event_json(gatya_event, now))
+        .collect();
+    writeln!(writer, "{}", serde_json::to_string_pretty(&events)?)?;
+    Ok(())
+}
+
+pub fn write_events_csv<W: Write>(
+    writer: W,
+    gatya_events: &[GatyaEvent],
+    now: NaiveDateTime,
+) -> error::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record([
+        "gatya_id",
+        "type",
+        "banner",
+        "start",
+        "end",
+        "status",
+        "price",
+        "rare_chance",
+        "super_rare_chance",
+        "uber_rare_chance",
+        "legend_rare_chance",
+        "guaranteed",
+        "tags",
+    ])?;
+    for gatya_event in gatya_events.iter() {
+        writer.write_record([
+            gatya_event.gatya_id.to_string(),
+            get_type_name(gatya_event.gatya_type),
+            gatya_event.banner_txt.clone(),
+            gatya_event.start.format(DATE_FORMAT).to_string(),
+            gatya_event.end.format(DATE_FORMAT).to_string(),
+            get_status_name(gatya_event.get_status(now)).to_string(),
+            gatya_event.price.to_string(),
+            gatya_event.rare_chance.to_string(),
+            gatya_event.super_rare_chance.to_string(),
+            gatya_event.uber_rare_chance.to_string(),
+            gatya_event.legend_rare_chance.to_string(),
+            gatya_event.guaranteed.to_string(),
+            gatya_event.get_tags().join(";"),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// One entry of a search pattern, with the cat filled in when the slot is known
+fn get_pattern_json(
+    cat: (u32, u32),
+    gatya_slot_data: &[Vec<i32>],
+    cat_names: &HashMap<i32, String>,
+) -> serde_json::Value {
+    let (rarity, slot) = cat;
+    let guaranteed: bool = rarity == GUARANTEED_UBER;
+    let rarity: u32 = if guaranteed { 2 } else { rarity };
+    let slot: Option<u32> = if slot == IGNORE_SLOT || rarity == BLANK_SLOT {
+        None
+    } else {
+        Some(slot)
+    };
+    let cat_id: Option<i32> = slot.and_then(|slot| {
+        gatya_slot_data
+            .get(rarity as usize)
+            .and_then(|rarity_data| rarity_data.get(slot as usize))
+            .copied()
+    });
+    json!({
+        "rarity": simulator::get_rarity_name(rarity),
+        "slot": slot,
+        "cat_id": cat_id,
+        "name": cat_id.and_then(|cat_id| cat_names.get(&cat_id)),
+        "guaranteed": guaranteed,
+    })
+}
+
+// The pattern's cat IDs and their names, with the rarity for entries without a known cat
+fn get_pattern_text(
+    cats: &[(u32, u32)],
+    gatya_slot_data: &[Vec<i32>],
+    cat_names: &HashMap<i32, String>,
+) -> (String, String) {
+    let mut ids: Vec<String> = Vec::new();
+    let mut names: Vec<String> = Vec::new();
+    for cat in cats.iter() {
+        let entry: serde_json::Value = get_pattern_json(*cat, gatya_slot_data, cat_names);
+        let rarity: &str = entry["rarity"].as_str().unwrap_or("");
+        match entry["cat_id"].as_i64() {
+            Some(cat_id) => ids.push(cat_id.to_string()),
+            None => ids.push(rarity.to_string()),
+        }
+        match entry["name"].as_str() {
+            Some(name) => names.push(name.to_string()),
+            None => names.push(ids[ids.len() - 1].clone()),
+        }
+    }
+    (ids.join(";"), names.join(";"))
+}
+
+pub struct SeedSearch<'a> {
+    pub gatya_event: &'a GatyaEvent,
+    pub gatya_slot_data: &'a [Vec<i32>],
+    pub cat_names: &'a HashMap<i32, String>,
+    pub cats: &'a [(u32, u32)],
+    pub draw_mode: &'a DrawMode,
+    pub seeds: &'a [u32],
+    pub duration: std::time::Duration,
+}
+
+pub fn write_seeds_json<W: Write>(
+    mut writer: W,
+    search: &SeedSearch,
+    now: NaiveDateTime,
+) -> error::Result<()> {
+    let pattern: Vec<serde_json::Value> = search
+        .cats
+        .iter()
+        .map(|cat| get_pattern_json(*cat, search.gatya_slot_data, search.cat_names))
+        .collect();
+    let seeds: Vec<serde_json::Value> = search
+        .seeds
+        .iter()
+        .map(|seed| {
+            json!({
+                "seed": seed,
+                "end_seed": simulator::get_end_seed(*seed, search.cats),
+            })
+        })
+        .collect();
+    let result: serde_json::Value = json!({
+        "banner": get_event_json(search.gatya_event, now),
+        "draw": get_draw_mode_json(search.draw_mode),
+        "pattern": pattern,
+        "next_position": search.cats.len(),
+        "seed_count": search.seeds.len(),
+        "seeds": seeds,
+        "duration_ms": search.duration.as_millis() as u64,
+    });
+    writeln!(writer, "{}", serde_json::to_string_pretty(&result)?)?;
+    Ok(())
+}
+
+// Every row repeats the banner and pattern so each one stands on its own
+pub fn write_seeds_csv<W: Write>(writer: W, search: &SeedSearch) -> error::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record([
+        "seed",
+        "end_seed",
+        "gatya_id",
+        "banner",
+        "pattern",
+        "pattern_names",
+        "duration_ms",
+    ])?;
+    let (pattern, pattern_names) =
+        get_pattern_text(search.cats, search.gatya_slot_data, search.cat_names);
+    let duration_ms: String = search.duration.as_millis().to_string();
+    for seed in search.seeds.iter() {
+        writer.write_record([
+            seed.to_string(),
+            simulator::get_end_seed(*seed, search.cats).to_string(),
+            search.gatya_event.gatya_id.to_string(),
+            search.gatya_event.banner_txt.clone(),
+            pattern.clone(),
+            pattern_names.clone(),
+            duration_ms.clone(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::gatya_data::GatyaFlags;
+    use crate::mock_server::tests::{get_test_event, get_test_slots};
+
+    fn get_now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    }
+
+    // Cat 4, cat 13, then an uber only known by its rarity
+    const CATS: [(u32, u32); 3] = [(0, 1), (1, 3), (2, IGNORE_SLOT)];
+
+    fn write_seeds(json: bool) -> String {
+        let gatya_event: GatyaEvent = get_test_event(true, GatyaFlags::empty());
+        let gatya_slot_data: Vec<Vec<i32>> = get_test_slots();
+        let cat_names: HashMap<i32, String> = HashMap::from([(4, "Mock Cat 4".to_string())]);
+        let search: SeedSearch = SeedSearch {
+            gatya_event: &gatya_event,
+            gatya_slot_data: &gatya_slot_data,
+            cat_names: &cat_names,
+            cats: &CATS,
+            draw_mode: &DrawMode::Single,
+            seeds: &[123456789],
+            duration: std::time::Duration::from_millis(1500),
+        };
+        let mut data: Vec<u8> = Vec::new();
+        if json {
+            write_seeds_json(&mut data, &search, get_now()).unwrap();
+        } else {
+            write_seeds_csv(&mut data, &search).unwrap();
+        }
+        String::from_utf8(data).unwrap()
+    }
+
+    #[test]
+    fn writes_seeds_as_json() {
+        let result: serde_json::Value = serde_json::from_str(&write_seeds(true)).unwrap();
+        assert_eq!(result["banner"]["gatya_id"], 1);
+        assert_eq!(result["banner"]["status"], "past");
+        assert_eq!(result["draw"]["mode"], "single");
+        assert_eq!(result["next_position"], 3);
+        assert_eq!(result["seed_count"], 1);
+        assert_eq!(result["seeds"][0]["seed"], 123456789);
+        assert_eq!(
+            result["seeds"][0]["end_seed"],
+            simulator::get_end_seed(123456789, &CATS)
+        );
+        assert_eq!(result["duration_ms"], 1500);
+        assert_eq!(
+            result["pattern"],
+            json!([
+                {"rarity": "Rare", "slot": 1, "cat_id": 4, "name": "Mock Cat 4", "guaranteed": false},
+                {"rarity": "Super Rare", "slot": 3, "cat_id": 13, "name": null, "guaranteed": false},
+                {"rarity": "Uber Rare", "slot": null, "cat_id": null, "name": null, "guaranteed": false},
+            ])
+        );
+    }
+
+    #[test]
+    fn writes_seeds_as_csv() {
+        let data: String = write_seeds(false);
+        let lines: Vec<&str> = data.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "seed,end_seed,gatya_id,banner,pattern,pattern_names,duration_ms".to_string(),
+                format!(
+                    "123456789,{},1,Mock Banner,4;13;Uber Rare,Mock Cat 4;13;Uber Rare,1500",
+                    simulator::get_end_seed(123456789, &CATS)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_events_as_json_and_csv() {
+        let gatya_events: [GatyaEvent; 1] = [get_test_event(true, GatyaFlags::empty())];
+        let mut data: Vec<u8> = Vec::new();
+        write_events_json(&mut data, &gatya_events, get_now()).unwrap();
+        let events: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        assert_eq!(events.as_array().map(|events| events.len()), Some(1));
+        assert_eq!(events[0]["banner"], "Mock Banner");
+        assert_eq!(events[0]["start"], "2020-01-01 11:00");
+        assert_eq!(events[0]["tags"], json!(["guaranteed"]));
+
+        let mut data: Vec<u8> = Vec::new();
+        write_events_csv(&mut data, &gatya_events, get_now()).unwrap();
+        let data: String = String::from_utf8(data).unwrap();
+        let lines: Vec<&str> = data.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("gatya_id,type,banner,start,end,status,"));
+        assert_eq!(
+            lines[1],
+            "1,rare,Mock Banner,2020-01-01 11:00,2020-01-01 11:00,past,0,7000,2500,470,30,true,guaranteed"
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use std::path::{Path, PathBuf};
+
+use crate::cli::DrawArg;
+use crate::error::{self, Error};
+
+// A saved search, e.g.
+// {"region": "en", "gatya_id": 123, "cats": [308, "Bahamut", "#3", -2], "draw": "single"}
+// The banner can be given by "gatya_id", by "banner" text like the events filter, or both
+pub struct PatternFile {
+    pub region: String,
+    pub gatya_id: Option<u32>,
+    pub banner: Option<String>,
+    pub cats: Option<Vec<String>>,
+    pub rarities: Option<Vec<String>>,
+    pub draw: Option<DrawArg>,
+    pub step: usize,
+    pub steps: Option<String>,
+    pub threads: Option<u32>,
+}
+
+// Entries can be numbers or strings, both are handled like --cats and --rarities
+fn get_entries(json: &serde_json::Value, key: &str) -> Result<Option<Vec<String>>, String> {
+    let values: &Vec<serde_json::Value> = match &json[key] {
+        serde_json::Value::Null => return Ok(None),
+        serde_json::Value::Array(values) => values,
+        _ => return Err(format!("{} must be a list", key)),
+    };
+    let mut entries: Vec<String> = Vec::new();
+    for value in values.iter() {
+        match value {
+            serde_json::Value::String(entry) => entries.push(entry.clone()),
+            serde_json::Value::Number(entry) => entries.push(entry.to_string()),
+            _ => return Err(format!("invalid {} entry {}", key, value)),
+        }
+    }
+    Ok(Some(entries))
+}
+
+fn get_draw(json: &serde_json::Value) -> Result<Option<DrawArg>, String> {
+    match &json["draw"] {
+        serde_json::Value::Null => Ok(None),
+        serde_json::Value::String(draw) => match draw.as_str() {
+            "single" => Ok(Some(DrawArg::Single)),
+            "eleven" => Ok(Some(DrawArg::Eleven)),
+            "step-up" => Ok(Some(DrawArg::StepUp)),
+            _ => Err(format!("unknown draw {:?}", draw)),
+        },
+        draw => Err(format!("invalid draw {}", draw)),
+    }
+}
+
+fn get_u32(json: &serde_json::Value, key: &str) -> Result<Option<u32>, String> {
+    let value: u64 = match &json[key] {
+        serde_json::Value::Null => return Ok(None),
+        value => match value.as_u64() {
+            Some(value) => value,
+            None => return Err(format!("{} must be a positive number", key)),
+        },
+    };
+    match u32::try_from(value) {
+        Ok(value) => Ok(Some(value)),
+        Err(_) => Err(format!("{} {} is too large", key, value)),
+    }
+}
+
+fn get_string(json: &serde_json::Value, key: &str) -> Result<Option<String>, String> {
+    match &json[key] {
+        serde_json::Value::Null => Ok(None),
+        serde_json::Value::String(value) if !value.trim().is_empty() => {
+            Ok(Some(value.trim().to_string()))
+        }
+        _ => Err(format!("{} must be non-empty text", key)),
+    }
+}
+
+fn parse_pattern(json: &serde_json::Value) -> Result<PatternFile, String> {
+    let region: String = match json["region"].as_str() {
+        Some(region) => region.to_string(),
+        None => return Err("missing region".to_string()),
+    };
+    let gatya_id: Option<u32> = get_u32(json, "gatya_id")?;
+    let banner: Option<String> = get_string(json, "banner")?;
+    if gatya_id.is_none() && banner.is_none() {
+        return Err("needs a gatya_id or banner".to_string());
+    }
+    let cats: Option<Vec<String>> = get_entries(json, "cats")?;
+    let rarities: Option<Vec<String>> = get_entries(json, "rarities")?;
+    if cats.is_some() == rarities.is_some() {
+        return Err("needs either cats or rarities".to_string());
+    }
+    Ok(PatternFile {
+        region,
+        gatya_id,
+        banner,
+        cats,
+        rarities,
+        draw: get_draw(json)?,
+        step: get_u32(json, "step")?.unwrap_or(1) as usize,
+        steps: get_string(json, "steps")?,
+        threads: get_u32(json, "threads")?,
+    })
+}
+
+pub fn read_pattern_file(path: &Path) -> error::Result<PatternFile> {
+    let data: String = std::fs::read_to_string(path)?;
+    let json: serde_json::Value = serde_json::from_str(&data)
+        .map_err(|err| Error::InvalidInput(format!("not valid JSON: {}", err)))?;
+    parse_pattern(&json).map_err(Error::InvalidInput)
+}
+
+fn is_result_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.contains(".result."))
+        .unwrap_or(false)
+}
+
+// Directories are expanded to the pattern files directly inside them, skipping earlier results
+pub fn get_pattern_paths(paths: &[PathBuf]) -> error::Result<Vec<PathBuf>> {
+    let mut pattern_paths: Vec<PathBuf> = Vec::new();
+    for path in paths.iter() {
+        if !path.is_dir() {
+            pattern_paths.push(path.clone());
+            continue;
+        }
+        let mut dir_paths: Vec<PathBuf> = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let entry_path: PathBuf = entry?.path();
+            let is_json: bool = entry_path
+                .extension()
+                .map(|extension| extension == "json")
+                .unwrap_or(false);
+            if entry_path.is_file() && is_json && !is_result_file(&entry_path) {
+                dir_paths.push(entry_path);
+            }
+        }
+        dir_paths.sort();
+        pattern_paths.append(&mut dir_paths);
+    }
+    Ok(pattern_paths)
+}
+
+pub fn get_result_path(pattern_path: &Path, out_dir: Option<&Path>, extension: &str) -> PathBuf {
+    let stem: String = pattern_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "pattern".to_string());
+    let file_name: String = format!("{}.result.{}", stem, extension);
+    match out_dir {
+        Some(out_dir) => out_dir.join(file_name),
+        None => pattern_path.with_file_name(file_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_a_pattern() {
+        let pattern: PatternFile = parse_pattern(&json!({
+            "region": "en",
+            "gatya_id": 123,
+            "cats": [308, "Bahamut", "#3", -2],
+            "draw": "step-up",
+            "step": 2,
+            "steps": "11:750,11:1500:uber",
+            "threads": 8
+        }))
+        .unwrap();
+        assert_eq!(pattern.region, "en");
+        assert_eq!(pattern.gatya_id, Some(123));
+        assert_eq!(pattern.banner, None);
+        assert_eq!(
+            pattern.cats,
+            Some(vec![
+                "308".to_string(),
+                "Bahamut".to_string(),
+                "#3".to_string(),
+                "-2".to_string()
+            ])
+        );
+        assert!(pattern.rarities.is_none());
+        assert!(matches!(pattern.draw, Some(DrawArg::StepUp)));
+        assert_eq!(pattern.step, 2);
+        assert_eq!(pattern.steps.as_deref(), Some("11:750,11:1500:uber"));
+        assert_eq!(pattern.threads, Some(8));
+    }
+
+    #[test]
+    fn parses_a_pattern_by_banner_text() {
+        let pattern: PatternFile = parse_pattern(&json!({
+            "region": "jp",
+            "banner": " Legend Rare Festival ",
+            "rarities": ["rare", "uber", "blank"]
+        }))
+        .unwrap();
+        assert_eq!(pattern.gatya_id, None);
+        assert_eq!(pattern.banner.as_deref(), Some("Legend Rare Festival"));
+        assert_eq!(pattern.rarities.map(|rarities| rarities.len()), Some(3));
+        assert!(pattern.draw.is_none());
+        assert_eq!(pattern.step, 1);
+    }
+
+    #[test]
+    fn rejects_bad_patterns() {
+        let bad_patterns: [serde_json::Value; 9] = [
+            json!({"gatya_id": 1, "cats": [1]}),
+            json!({"region": "en", "cats": [1]}),
+            json!({"region": "en", "gatya_id": 1}),
+            json!({"region": "en", "gatya_id": 1, "cats": [1], "rarities": ["rare"]}),
+            json!({"region": "en", "gatya_id": 4294967296_u64, "cats": [1]}),
+            json!({"region": "en", "gatya_id": -1, "cats": [1]}),
+            json!({"region": "en", "banner": "", "cats": [1]}),
+            json!({"region": "en", "gatya_id": 1, "cats": [1], "threads": 4294967296_u64}),
+            json!({"region": "en", "gatya_id": 1, "cats": [1], "draw": "triple"}),
+        ];
+        for json in bad_patterns.iter() {
+            assert!(parse_pattern(json).is_err(), "{} was accepted", json);
+        }
+    }
+
+    #[test]
+    fn expands_pattern_directories() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        for file_name in ["b.json", "a.json", "a.result.json", "notes.txt"] {
+            std::fs::write(dir.path().join(file_name), "{}").unwrap();
+        }
+        std::fs::create_dir(dir.path().join("nested.json")).unwrap();
+        let single: PathBuf = PathBuf::from("single.json");
+        let paths: Vec<PathBuf> =
+            get_pattern_paths(&[dir.path().to_path_buf(), single.clone()]).unwrap();
+        assert_eq!(
+            paths,
+            vec![dir.path().join("a.json"), dir.path().join("b.json"), single]
+        );
+    }
+
+    #[test]
+    fn names_result_files() {
+        let pattern_path: &Path = Path::new("patterns/bahamut.json");
+        assert_eq!(
+            get_result_path(pattern_path, None, "json"),
+            PathBuf::from("patterns/bahamut.result.json")
+        );
+        assert_eq!(
+            get_result_path(pattern_path, Some(Path::new("results")), "csv"),
+            PathBuf::from("results/bahamut.result.csv")
+        );
+    }
+}
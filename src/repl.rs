@@ -0,0 +1,561 @@
+use rustyline::{error::ReadlineError, DefaultEditor};
+use std::{
+    path::PathBuf,
+    sync::{atomic::AtomicU64, Arc},
+    time::Duration,
+};
+
+use crate::gatya_data::{self, GatyaEvent};
+use crate::simulator::{self, DrawMode};
+use crate::{cache, cli, error, Banner, BLANK_SLOT, BLANK_SLOT_USER, IGNORE_SLOT};
+
+const HELP: &str = "Commands:
+  add <cats>          add rolled cats by ID, name or #number, e.g. `add 308` or `add 4, 13, #2`
+  blank               add a roll you don't know
+  rarity <rarities>   add rolls by rarity only: rare, super, uber, legend or blank
+  undo                undo the last change
+  show                show the banner, your rolls and the current seeds
+  search              find seeds, reusing the seeds from the last search when possible
+  draw <mode> [step] [steps]
+                      set how the cats were rolled: single, eleven or step-up, with the
+                      step-up layout as ROLLS:PRICE steps, e.g. `draw step-up 2 11:750,11:1500:uber`
+  threads <count>     set the number of threads to search with
+  clear               remove every roll and seed
+  switch-banner       pick another banner
+  help                show this list
+  quit                leave";
+
+#[derive(Clone)]
+enum Entry {
+    Cat(i32),
+    Rarity(u32),
+}
+
+// Seeds found for the first `pattern_len` rolls, later searches only check these again
+#[derive(Clone)]
+struct Candidates {
+    seeds: Vec<u32>,
+    pattern_len: usize,
+}
+
+// Everything `undo` can go back to
+#[derive(Clone)]
+struct State {
+    entries: Vec<Entry>,
+    draw_mode: DrawMode,
+    candidates: Option<Candidates>,
+}
+
+struct Session {
+    cc: String,
+    offline: bool,
+    banner: Banner,
+    thread_count: u32,
+    state: State,
+    undo_stack: Vec<State>,
+}
+
+fn get_draw_mode_name(draw_mode: &DrawMode) -> String {
+    match draw_mode {
+        DrawMode::Single => "single draws".to_string(),
+        DrawMode::ElevenDraws => "11 draws".to_string(),
+        DrawMode::StepUp(step_up) => format!("step-up from step {}", step_up.current_step + 1),
+    }
+}
+
+// Same default as the prompts: step-up banners start at step 1, everything else is single draws
+fn get_default_draw_mode(gatya_event: &GatyaEvent, total_uber_rares: usize) -> DrawMode {
+    if gatya_event.flags.contains(gatya_data::GatyaFlags::STEP_UP) && total_uber_rares > 0 {
+        DrawMode::StepUp(simulator::StepUp::new(crate::get_assumed_steps(), 0))
+    } else {
+        DrawMode::Single
+    }
+}
+
+// Commas separate entries; without them, a list of IDs or #numbers is split on spaces
+// and anything else is taken as one name
+fn split_cats(args: &str) -> Vec<String> {
+    if args.contains(',') {
+        return args.split(',').map(|arg| arg.trim().to_string()).collect();
+    }
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let is_numbers: bool = words
+        .iter()
+        .all(|word| word.parse::<i32>().is_ok() || word.starts_with('#'));
+    if is_numbers {
+        words.iter().map(|word| word.to_string()).collect()
+    } else {
+        vec![args.trim().to_string()]
+    }
+}
+
+fn split_rarities(args: &str) -> Vec<String> {
+    if args.contains(',') {
+        args.split(',').map(|arg| arg.trim().to_string()).collect()
+    } else {
+        args.split_whitespace().map(|arg| arg.to_string()).collect()
+    }
+}
+
+impl Session {
+    fn get_entry_display(&self, entry: &Entry) -> String {
+        match entry {
+            Entry::Cat(cat_id) => crate::get_cat_entry_display(*cat_id, &self.banner.cat_names),
+            Entry::Rarity(BLANK_SLOT) => "Blank".to_string(),
+            Entry::Rarity(rarity) => format!("any {}", simulator::get_rarity_name(*rarity)),
+        }
+    }
+
+    fn get_pattern(&self) -> error::Result<Vec<(u32, u32)>> {
+        let mut cats: Vec<(u32, u32)> = Vec::new();
+        for entry in self.state.entries.iter() {
+            match entry {
+                Entry::Cat(cat_id) => cats.append(&mut gatya_data::get_cat_list_from_ids(
+                    self.banner.gatya_slot_data.clone(),
+                    vec![*cat_id],
+                )?),
+                Entry::Rarity(rarity) => cats.push((*rarity, IGNORE_SLOT)),
+            }
+        }
+        Ok(crate::get_search_pattern(
+            &self.banner.gatya_event,
+            cats,
+            &self.state.draw_mode,
+        ))
+    }
+
+    fn save_state(&mut self) {
+        self.undo_stack.push(self.state.clone());
+    }
+
+    fn add_entries(&mut self, entries: Vec<Entry>) {
+        self.save_state();
+        for entry in entries.into_iter() {
+            println!("  {}", self.get_entry_display(&entry));
+            self.state.entries.push(entry);
+        }
+    }
+
+    fn undo(&mut self) {
+        match self.undo_stack.pop() {
+            Some(state) => {
+                self.state = state;
+                println!("Undone, {} rolls entered", self.state.entries.len());
+            }
+            None => println!("Nothing to undo."),
+        }
+    }
+
+    fn set_draw_mode(&mut self, args: &str) -> error::Result<()> {
+        let words: Vec<&str> = args.split_whitespace().collect();
+        let draw: cli::DrawArg = match words.first().copied() {
+            Some("single") => cli::DrawArg::Single,
+            Some("eleven") => cli::DrawArg::Eleven,
+            Some("step-up") => cli::DrawArg::StepUp,
+            _ => {
+                return Err(error::Error::InvalidInput(
+                    "expected `draw single`, `draw eleven` or `draw step-up <step> [steps]`"
+                        .to_string(),
+                ))
+            }
+        };
+        let step: usize = match words.get(1) {
+            Some(step) => step.parse::<usize>().map_err(|_| {
+                error::Error::InvalidInput(format!("{:?} is not a step number", step))
+            })?,
+            None => 1,
+        };
+        let draw_args: cli::DrawArgs = cli::DrawArgs {
+            draw: Some(draw),
+            step,
+            steps: words.get(2).map(|steps| steps.to_string()),
+        };
+        let draw_mode: DrawMode = crate::get_draw_mode(
+            &draw_args,
+            &self.banner.gatya_event,
+            self.banner.gatya_slot_data[2].len() as u32,
+        )?;
+        self.save_state();
+        // seeds found for another draw mode don't carry over
+        self.state.candidates = None;
+        self.state.draw_mode = draw_mode;
+        println!("Draw mode: {}", get_draw_mode_name(&self.state.draw_mode));
+        Ok(())
+    }
+
+    fn show(&self) -> error::Result<()> {
+        println!(
+            "Banner: {} (gatya id {}, {})",
+            self.banner.gatya_event.banner_txt, self.banner.gatya_event.gatya_id, self.cc
+        );
+        println!("Draw mode: {}", get_draw_mode_name(&self.state.draw_mode));
+        if self.state.entries.is_empty() {
+            println!("No rolls entered yet.");
+        }
+        for (i, entry) in self.state.entries.iter().enumerate() {
+            println!("{}. {}", i + 1, self.get_entry_display(entry));
+        }
+        let candidates: &Candidates = match &self.state.candidates {
+            Some(candidates) => candidates,
+            None => return Ok(()),
+        };
+        println!(
+            "\nSeeds matching the first {} rolls: {}",
+            candidates.pattern_len,
+            candidates.seeds.len()
+        );
+        if candidates.pattern_len > self.state.entries.len() {
+            return Ok(());
+        }
+        let cats: Vec<(u32, u32)> = self.get_pattern()?;
+        let seeds: Vec<u32> = self.get_matching_seeds(&candidates.seeds, &cats);
+        if seeds.len() < candidates.seeds.len() {
+            println!("Still matching with every roll: {}", seeds.len());
+        }
+        if !seeds.is_empty() {
+            crate::print_seeds(&self.banner, &cats, &seeds, &self.state.draw_mode);
+        }
+        Ok(())
+    }
+
+    fn get_matching_seeds(&self, seeds: &[u32], cats: &[(u32, u32)]) -> Vec<u32> {
+        seeds
+            .iter()
+            .copied()
+            .filter(|seed| {
+                simulator::matches_pattern(
+                    *seed,
+                    &self.banner.gatya_event,
+                    &self.banner.gatya_slot_data,
+                    cats,
+                    &self.state.draw_mode,
+                )
+            })
+            .collect()
+    }
+
+    fn search(&mut self) -> error::Result<()> {
+        if self.state.entries.is_empty() {
+            println!("Add the cats you rolled first.");
+            return Ok(());
+        }
+        let cats: Vec<(u32, u32)> = self.get_pattern()?;
+        let previous: Option<Candidates> = self
+            .state
+            .candidates
+            .clone()
+            .filter(|candidates| !candidates.seeds.is_empty())
+            .filter(|candidates| candidates.pattern_len <= cats.len());
+        let (seeds, duration): (Vec<u32>, Duration) = match previous {
+            Some(candidates) => {
+                println!(
+                    "Checking the {} seeds from the last search...",
+                    candidates.seeds.len()
+                );
+                (
+                    self.get_matching_seeds(&candidates.seeds, &cats),
+                    Duration::ZERO,
+                )
+            }
+            None => {
+                if crate::is_collisions(cats.clone(), self.banner.gatya_slot_data[0].len() as u32) {
+                    eprintln!(
+                        "WARNING: There might be a duplicate rare cat! The seed might not be found."
+                    )
+                }
+                eprintln!("\nFinding seed...");
+                let (_, seeds, duration) = crate::find_banner_seeds(
+                    &self.banner,
+                    cats.clone(),
+                    &self.state.draw_mode,
+                    self.thread_count,
+                    Arc::new(AtomicU64::new(0)),
+                );
+                (seeds, duration)
+            }
+        };
+        self.save_state();
+        self.state.candidates = Some(Candidates {
+            seeds: seeds.clone(),
+            pattern_len: cats.len(),
+        });
+        println!();
+        crate::print_seeds(&self.banner, &cats, &seeds, &self.state.draw_mode);
+        if !duration.is_zero() {
+            println!("\nTime taken to find seed: {:?}", duration);
+        }
+        Ok(())
+    }
+
+    async fn switch_banner(&mut self) -> error::Result<()> {
+        // the data was already updated when the session started, if it was going to be
+        let (gatya_event, _) = crate::select_event(&self.cc, self.offline, Some(false)).await?;
+        self.banner = crate::load_banner(&self.cc, gatya_event, self.offline, false).await?;
+        self.state = State {
+            entries: Vec::new(),
+            draw_mode: get_default_draw_mode(
+                &self.banner.gatya_event,
+                self.banner.gatya_slot_data[2].len(),
+            ),
+            candidates: None,
+        };
+        self.undo_stack.clear();
+        Ok(())
+    }
+
+    // Returns false once the session should end
+    async fn run_command(&mut self, line: &str) -> error::Result<bool> {
+        let (command, args): (&str, &str) = match line.split_once(char::is_whitespace) {
+            Some((command, args)) => (command, args.trim()),
+            None => (line, ""),
+        };
+        match command {
+            "add" => {
+                let cat_ids: Vec<i32> = crate::parse_cats(
+                    &split_cats(args),
+                    &self.banner.gatya_slot_data,
+                    &self.banner.cat_names,
+                )?;
+                self.add_entries(cat_ids.into_iter().map(Entry::Cat).collect());
+            }
+            "blank" => self.add_entries(vec![Entry::Cat(BLANK_SLOT_USER)]),
+            "rarity" => {
+                let rarities: Vec<i32> = crate::parse_rarities(&split_rarities(args))?;
+                self.add_entries(
+                    rarities
+                        .into_iter()
+                        .map(|rarity| Entry::Rarity(rarity as u32))
+                        .collect(),
+                );
+            }
+            "undo" => self.undo(),
+            "show" => self.show()?,
+            "search" => self.search()?,
+            "draw" => self.set_draw_mode(args)?,
+            "threads" => match args.parse::<u32>() {
+                Ok(thread_count) if thread_count > 0 => {
+                    self.thread_count = thread_count;
+                    println!("Searching with {} threads", thread_count);
+                }
+                _ => println!("Expected a number of threads, e.g. `threads 8`."),
+            },
+            "clear" => {
+                self.save_state();
+                self.state.entries.clear();
+                self.state.candidates = None;
+            }
+            "switch-banner" => self.switch_banner().await?,
+            "help" | "?" => println!("{}", HELP),
+            "quit" | "exit" => return Ok(false),
+            _ => println!("Unknown command {:?}, type `help` for a list.", command),
+        }
+        Ok(true)
+    }
+}
+
+// Asks for the region and banner once, then keeps them for every command
+pub async fn run_repl(offline: bool) -> error::Result<()> {
+    let cc: String = crate::select_cc();
+    println!();
+    let (gatya_event, update) = crate::select_event(&cc, offline, None).await?;
+    let banner: Banner = crate::load_banner(&cc, gatya_event, offline, update).await?;
+    let mut session: Session = Session {
+        cc,
+        offline,
+        state: State {
+            entries: Vec::new(),
+            draw_mode: get_default_draw_mode(&banner.gatya_event, banner.gatya_slot_data[2].len()),
+            candidates: None,
+        },
+        banner,
+        thread_count: crate::get_default_thread_count(),
+        undo_stack: Vec::new(),
+    };
+
+    let history_path: PathBuf = cache::get_data_path("history.txt");
+    let mut editor: DefaultEditor = DefaultEditor::new()?;
+    // there's no history on the first run
+    let _ = editor.load_history(&history_path);
+    println!("\nType `help` for a list of commands.");
+    loop {
+        let line: String = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        };
+        let line: &str = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line)?;
+        match session.run_command(line).await {
+            Ok(true) => {}
+            Ok(false) => break,
+            // errors only end the command, the session and its data are kept
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+    editor.save_history(&history_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::gatya_data::GatyaFlags;
+    use crate::mock_server::tests::{get_test_event, get_test_slots};
+
+    fn get_test_session() -> Session {
+        let gatya_event: GatyaEvent = get_test_event(false, GatyaFlags::empty());
+        let gatya_slot_data: Vec<Vec<i32>> = get_test_slots();
+        Session {
+            cc: "en".to_string(),
+            offline: true,
+            state: State {
+                entries: Vec::new(),
+                draw_mode: get_default_draw_mode(&gatya_event, gatya_slot_data[2].len()),
+                candidates: None,
+            },
+            banner: Banner {
+                gatya_event,
+                gatya_slot_data,
+                cat_names: HashMap::new(),
+            },
+            thread_count: 1,
+            undo_stack: Vec::new(),
+        }
+    }
+
+    fn get_entries(session: &Session) -> Vec<String> {
+        session
+            .state
+            .entries
+            .iter()
+            .map(|entry| session.get_entry_display(entry))
+            .collect()
+    }
+
+    #[test]
+    fn splits_cats() {
+        assert_eq!(split_cats("4, 13, #2"), vec!["4", "13", "#2"]);
+        assert_eq!(split_cats("308 50 #3"), vec!["308", "50", "#3"]);
+        assert_eq!(split_cats(" Crazed Cat "), vec!["Crazed Cat"]);
+        assert_eq!(split_cats("Bahamut, 4"), vec!["Bahamut", "4"]);
+        assert_eq!(split_rarities("rare uber"), vec!["rare", "uber"]);
+        assert_eq!(split_rarities("rare, blank"), vec!["rare", "blank"]);
+    }
+
+    #[tokio::test]
+    async fn undoes_changes() {
+        let mut session: Session = get_test_session();
+        session.run_command("add 4").await.unwrap();
+        session.run_command("add 13 12").await.unwrap();
+        session.run_command("rarity rare").await.unwrap();
+        assert_eq!(
+            get_entries(&session),
+            vec!["cat 4", "cat 13", "cat 12", "any Rare"]
+        );
+
+        session.run_command("undo").await.unwrap();
+        assert_eq!(get_entries(&session), vec!["cat 4", "cat 13", "cat 12"]);
+        session.run_command("clear").await.unwrap();
+        assert!(session.state.entries.is_empty());
+        session.run_command("undo").await.unwrap();
+        assert_eq!(get_entries(&session), vec!["cat 4", "cat 13", "cat 12"]);
+        session.run_command("undo").await.unwrap();
+        session.run_command("undo").await.unwrap();
+        assert!(session.state.entries.is_empty());
+        // nothing left to undo
+        session.run_command("undo").await.unwrap();
+        assert!(session.state.entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_entries_without_changing_anything() {
+        let mut session: Session = get_test_session();
+        session.run_command("add 4").await.unwrap();
+        assert!(session.run_command("add 99").await.is_err());
+        assert!(session.run_command("rarity mythic").await.is_err());
+        assert!(session.run_command("draw triple").await.is_err());
+        assert_eq!(get_entries(&session), vec!["cat 4"]);
+        assert_eq!(session.undo_stack.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn narrows_down_the_last_search() {
+        let mut session: Session = get_test_session();
+        session.run_command("add 4, 13").await.unwrap();
+        session.state.candidates = Some(Candidates {
+            seeds: vec![1, 123456789, 987654321],
+            pattern_len: 2,
+        });
+
+        session.run_command("add 12").await.unwrap();
+        session.run_command("search").await.unwrap();
+        let candidates: &Candidates = session.state.candidates.as_ref().unwrap();
+        assert_eq!(candidates.seeds, vec![123456789]);
+        assert_eq!(candidates.pattern_len, 3);
+
+        // undoing the search brings the earlier seeds back
+        session.run_command("undo").await.unwrap();
+        let candidates: &Candidates = session.state.candidates.as_ref().unwrap();
+        assert_eq!(candidates.seeds.len(), 3);
+        assert_eq!(candidates.pattern_len, 2);
+    }
+
+    #[tokio::test]
+    async fn drops_seeds_from_another_draw_mode() {
+        let mut session: Session = get_test_session();
+        session.run_command("add 4").await.unwrap();
+        session.state.candidates = Some(Candidates {
+            seeds: vec![123456789],
+            pattern_len: 1,
+        });
+        session.banner.gatya_event.guaranteed = true;
+        session.run_command("draw eleven").await.unwrap();
+        assert!(session.state.candidates.is_none());
+        assert!(matches!(session.state.draw_mode, DrawMode::ElevenDraws));
+        session.run_command("undo").await.unwrap();
+        assert!(session.state.candidates.is_some());
+        assert!(matches!(session.state.draw_mode, DrawMode::Single));
+    }
+
+    #[tokio::test]
+    async fn rejects_draw_modes_the_banner_lacks() {
+        let mut session: Session = get_test_session();
+        assert!(session.run_command("draw eleven").await.is_err());
+        assert!(session.run_command("draw step-up 4").await.is_err());
+
+        // a step-up banner without ubers has nothing to guarantee
+        session.banner.gatya_event.flags = GatyaFlags::STEP_UP;
+        session.banner.gatya_slot_data[2].clear();
+        assert!(session.run_command("draw step-up 4").await.is_err());
+        assert!(session.run_command("draw eleven").await.is_err());
+        assert!(matches!(session.state.draw_mode, DrawMode::Single));
+        assert!(session.undo_stack.is_empty());
+
+        session.banner.gatya_slot_data = vec![vec![0], vec![1], vec![2], vec![3]];
+        session.run_command("draw step-up 4").await.unwrap();
+        assert!(matches!(session.state.draw_mode, DrawMode::StepUp(_)));
+
+        // the banner's own layout replaces the assumed one
+        assert!(session
+            .run_command("draw step-up 3 5:300,5:600:uber")
+            .await
+            .is_err());
+        assert!(session.run_command("draw step-up 2 5:300,5").await.is_err());
+        session
+            .run_command("draw step-up 2 5:300,5:600:uber")
+            .await
+            .unwrap();
+        let step_up: &simulator::StepUp = match &session.state.draw_mode {
+            DrawMode::StepUp(step_up) => step_up,
+            _ => panic!("expected a step-up"),
+        };
+        assert_eq!(step_up.current_step, 1);
+        assert_eq!(step_up.steps.len(), 2);
+        assert!(step_up.is_guaranteed_position(4));
+    }
+}
@@ -0,0 +1,423 @@
+use crate::gatya_data::GatyaEvent;
+use crate::{BLANK_SLOT, GUARANTEED_UBER, IGNORE_SLOT, MODULUS};
+
+#[derive(Clone)]
+pub struct Roll {
+    pub rarity: u32,
+    pub slot: u32,
+    pub cat_id: i32,
+    pub guaranteed: bool,
+    pub step: Option<usize>,
+}
+
+pub fn advance_seed(seed: u32) -> u32 {
+    let mut seed: u32 = seed;
+    seed ^= seed << 13;
+    seed ^= seed >> 17;
+    seed ^= seed << 15;
+    seed
+}
+
+pub fn get_rarity(prob: u32, thresholds: (u32, u32, u32)) -> u32 {
+    let (super_rare_chance, uber_chance, legend_chance) = thresholds;
+    if prob < super_rare_chance {
+        0
+    } else if prob < uber_chance {
+        1
+    } else if prob < legend_chance {
+        2
+    } else {
+        3
+    }
+}
+
+#[derive(Clone)]
+pub struct Step {
+    pub rolls: usize,
+    pub price: u32,
+    pub guaranteed: bool,
+}
+
+// Step-up banners go through their steps in order and then keep repeating the last one
+#[derive(Clone)]
+pub struct StepUp {
+    pub steps: Vec<Step>,
+    pub current_step: usize,
+}
+
+// gatya.tsv only marks a banner as a step-up (flag 4), it has no columns for the steps
+// themselves, and there is no published layout to check against. This one, four 11-roll
+// steps for 750, 1500, 1500 and 1500 cat food with an uber guaranteed on the last, is only
+// assumed; --steps or a pattern file's "steps" replaces it for banners laid out differently.
+// The steps only change which rolls are guaranteed, the prices are just shown to the user.
+const STEP_UP_STEPS: [(usize, u32, bool); 4] = [
+    (11, 750, false),
+    (11, 1500, false),
+    (11, 1500, false),
+    (11, 1500, true),
+];
+
+pub fn get_default_steps() -> Vec<Step> {
+    STEP_UP_STEPS
+        .iter()
+        .map(|(rolls, price, guaranteed)| Step {
+            rolls: *rolls,
+            price: *price,
+            guaranteed: *guaranteed,
+        })
+        .collect()
+}
+
+// Steps are written ROLLS:PRICE, with :uber on the ones ending in a guaranteed uber,
+// e.g. "11:750,11:1500,11:1500,11:1500:uber"
+pub fn parse_steps(spec: &str) -> Result<Vec<Step>, String> {
+    let mut steps: Vec<Step> = Vec::new();
+    for part in spec.split(',') {
+        let fields: Vec<&str> = part.trim().split(':').collect();
+        let guaranteed: bool = match fields.get(2) {
+            None => false,
+            Some(&"uber") if fields.len() == 3 => true,
+            _ => return Err(format!("{:?} is not ROLLS:PRICE or ROLLS:PRICE:uber", part)),
+        };
+        let rolls: usize = match fields[0].parse::<usize>() {
+            Ok(rolls) if rolls > 0 => rolls,
+            _ => return Err(format!("{:?} is not a number of rolls", fields[0])),
+        };
+        let price: u32 = match fields.get(1).map(|price| price.parse::<u32>()) {
+            Some(Ok(price)) => price,
+            _ => return Err(format!("{:?} has no cat food price", part)),
+        };
+        steps.push(Step {
+            rolls,
+            price,
+            guaranteed,
+        });
+    }
+    Ok(steps)
+}
+
+pub fn format_steps(steps: &[Step]) -> String {
+    steps
+        .iter()
+        .map(|step| {
+            let guaranteed: &str = if step.guaranteed { ":uber" } else { "" };
+            format!("{}:{}{}", step.rolls, step.price, guaranteed)
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+impl StepUp {
+    pub fn new(steps: Vec<Step>, current_step: usize) -> StepUp {
+        StepUp {
+            steps,
+            current_step,
+        }
+    }
+
+    // Returns the step a roll is part of and its position within that step
+    pub fn get_step(&self, position: usize) -> (usize, usize) {
+        let mut step: usize = self.current_step.min(self.steps.len() - 1);
+        let mut position: usize = position;
+        while position >= self.steps[step].rolls {
+            position -= self.steps[step].rolls;
+            if step < self.steps.len() - 1 {
+                step += 1;
+            }
+        }
+        (step, position)
+    }
+
+    pub fn is_guaranteed_position(&self, position: usize) -> bool {
+        let (step, step_position) = self.get_step(position);
+        self.steps[step].guaranteed && step_position == self.steps[step].rolls - 1
+    }
+}
+
+#[derive(Clone)]
+pub enum DrawMode {
+    Single,
+    ElevenDraws,
+    StepUp(StepUp),
+}
+
+impl DrawMode {
+    // Positions in a roll sequence that are guaranteed ubers
+    pub fn is_guaranteed_position(&self, gatya_event: &GatyaEvent, position: usize) -> bool {
+        match self {
+            DrawMode::Single => false,
+            DrawMode::ElevenDraws => gatya_event.guaranteed && position % 11 == 10,
+            DrawMode::StepUp(step_up) => step_up.is_guaranteed_position(position),
+        }
+    }
+
+    pub fn get_step(&self, position: usize) -> Option<(usize, usize)> {
+        match self {
+            DrawMode::StepUp(step_up) => Some(step_up.get_step(position)),
+            _ => None,
+        }
+    }
+}
+
+pub fn roll(
+    seed: u32,
+    thresholds: (u32, u32, u32),
+    gatya_slot_data: &[Vec<i32>],
+    guaranteed: bool,
+    step: Option<usize>,
+) -> (Roll, u32) {
+    let mut seed: u32 = advance_seed(seed);
+    let rarity: u32 = if guaranteed {
+        2
+    } else {
+        let rarity: u32 = get_rarity(seed % MODULUS, thresholds);
+        seed = advance_seed(seed);
+        rarity
+    };
+
+    let pool: &[i32] = &gatya_slot_data[rarity as usize];
+    let slot: u32 = if pool.is_empty() {
+        0
+    } else {
+        seed % pool.len() as u32
+    };
+    let cat_id: i32 = pool.get(slot as usize).copied().unwrap_or(-1);
+    (
+        Roll {
+            rarity,
+            slot,
+            cat_id,
+            guaranteed,
+            step,
+        },
+        seed,
+    )
+}
+
+pub fn simulate_rolls(
+    seed: u32,
+    gatya_event: &GatyaEvent,
+    gatya_slot_data: &[Vec<i32>],
+    first_position: usize,
+    count: usize,
+    draw_mode: &DrawMode,
+) -> (Vec<Roll>, u32) {
+    let thresholds: (u32, u32, u32) = gatya_event.get_rarity_thresholds();
+    let mut seed: u32 = seed;
+    let mut rolls: Vec<Roll> = Vec::new();
+    for position in first_position..first_position + count {
+        let guaranteed: bool = draw_mode.is_guaranteed_position(gatya_event, position);
+        let step: Option<usize> = draw_mode.get_step(position).map(|(step, _)| step);
+        let (roll, next_seed) = roll(seed, thresholds, gatya_slot_data, guaranteed, step);
+        rolls.push(roll);
+        seed = next_seed;
+    }
+    (rolls, seed)
+}
+
+// Advances a seed past a search pattern, returning the seed the next roll starts from
+pub fn get_end_seed(seed: u32, cats: &[(u32, u32)]) -> u32 {
+    let mut seed: u32 = seed;
+    for cat in cats.iter() {
+        seed = advance_seed(seed);
+        if cat.0 != GUARANTEED_UBER {
+            seed = advance_seed(seed);
+        }
+    }
+    seed
+}
+
+// Checks a seed against a search pattern without searching again, e.g. after more cats are rolled
+pub fn matches_pattern(
+    seed: u32,
+    gatya_event: &GatyaEvent,
+    gatya_slot_data: &[Vec<i32>],
+    cats: &[(u32, u32)],
+    draw_mode: &DrawMode,
+) -> bool {
+    let (rolls, _) = simulate_rolls(seed, gatya_event, gatya_slot_data, 0, cats.len(), draw_mode);
+    rolls.iter().zip(cats.iter()).all(|(roll, cat)| {
+        let (rarity, slot) = *cat;
+        let rarity: u32 = if rarity == GUARANTEED_UBER { 2 } else { rarity };
+        rarity == BLANK_SLOT
+            || (roll.rarity == rarity && (slot == IGNORE_SLOT || roll.slot == slot))
+    })
+}
+
+// Guaranteed rolls can only be ubers, so anything else entered for them just matches any uber
+pub fn get_pattern_entry(rarity: u32, slot: u32, guaranteed: bool) -> (u32, u32) {
+    if !guaranteed {
+        return (rarity, slot);
+    }
+    if rarity == 2 && slot != IGNORE_SLOT {
+        (GUARANTEED_UBER, slot)
+    } else {
+        (GUARANTEED_UBER, IGNORE_SLOT)
+    }
+}
+
+pub fn get_rarity_name(rarity: u32) -> &'static str {
+    match rarity {
+        0 => "Rare",
+        1 => "Super Rare",
+        2 => "Uber Rare",
+        3 => "Legend Rare",
+        BLANK_SLOT => "Blank",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gatya_data::GatyaFlags;
+    use crate::mock_server::tests::{get_test_event, get_test_slots};
+
+    #[test]
+    fn advances_with_xorshift() {
+        assert_eq!(advance_seed(1), 268476417);
+        assert_eq!(advance_seed(123456789), 3620590185);
+    }
+
+    #[test]
+    fn simulates_single_draws() {
+        let (rolls, end_seed) = simulate_rolls(
+            123456789,
+            &get_test_event(false, GatyaFlags::empty()),
+            &get_test_slots(),
+            0,
+            12,
+            &DrawMode::Single,
+        );
+        let cat_ids: Vec<i32> = rolls.iter().map(|roll| roll.cat_id).collect();
+        assert_eq!(cat_ids, vec![4, 13, 12, 4, 8, 0, 5, 9, 12, 0, 1, 1]);
+        assert_eq!(end_seed, 1937278768);
+    }
+
+    #[test]
+    fn guaranteed_rolls_skip_the_rarity_roll() {
+        let event: GatyaEvent = get_test_event(true, GatyaFlags::empty());
+        let slots: Vec<Vec<i32>> = get_test_slots();
+        let (rolls, end_seed) =
+            simulate_rolls(123456789, &event, &slots, 0, 11, &DrawMode::ElevenDraws);
+        assert!(rolls[..10].iter().all(|roll| !roll.guaranteed));
+        assert!(rolls[10].guaranteed);
+        assert_eq!(rolls[10].rarity, 2);
+
+        // ten normal rolls advance twice each, the guaranteed one once
+        let mut seed: u32 = 123456789;
+        for _ in 0..21 {
+            seed = advance_seed(seed);
+        }
+        assert_eq!(end_seed, seed);
+        assert_eq!(rolls[10].slot, seed % slots[2].len() as u32);
+    }
+
+    #[test]
+    fn only_eleven_draws_on_guaranteed_banners_are_guaranteed() {
+        let event: GatyaEvent = get_test_event(false, GatyaFlags::empty());
+        assert!(!DrawMode::ElevenDraws.is_guaranteed_position(&event, 10));
+        let event: GatyaEvent = get_test_event(true, GatyaFlags::empty());
+        assert!(DrawMode::ElevenDraws.is_guaranteed_position(&event, 21));
+        assert!(!DrawMode::ElevenDraws.is_guaranteed_position(&event, 11));
+        assert!(!DrawMode::Single.is_guaranteed_position(&event, 10));
+    }
+
+    #[test]
+    fn step_up_goes_through_each_step_then_repeats_the_last() {
+        let step_up: StepUp = StepUp::new(get_default_steps(), 0);
+        assert_eq!(step_up.get_step(0), (0, 0));
+        assert_eq!(step_up.get_step(10), (0, 10));
+        assert_eq!(step_up.get_step(11), (1, 0));
+        assert_eq!(step_up.get_step(43), (3, 10));
+        assert_eq!(step_up.get_step(44), (3, 0));
+        assert!(!step_up.is_guaranteed_position(10));
+        assert!(!step_up.is_guaranteed_position(32));
+        assert!(step_up.is_guaranteed_position(43));
+        assert!(step_up.is_guaranteed_position(54));
+
+        let step_up: StepUp = StepUp::new(get_default_steps(), 3);
+        assert_eq!(step_up.get_step(0), (3, 0));
+        assert!(step_up.is_guaranteed_position(10));
+    }
+
+    #[test]
+    fn parses_steps() {
+        let steps: Vec<Step> = parse_steps("11:750, 5:300:uber").unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(
+            (steps[0].rolls, steps[0].price, steps[0].guaranteed),
+            (11, 750, false)
+        );
+        assert_eq!(
+            (steps[1].rolls, steps[1].price, steps[1].guaranteed),
+            (5, 300, true)
+        );
+        assert_eq!(
+            format_steps(&get_default_steps()),
+            "11:750,11:1500,11:1500,11:1500:uber"
+        );
+        let steps: Vec<Step> = parse_steps(&format_steps(&get_default_steps())).unwrap();
+        assert_eq!(steps.len(), 4);
+        assert!(steps[3].guaranteed);
+
+        assert!(parse_steps("").is_err());
+        assert!(parse_steps("11").is_err());
+        assert!(parse_steps("0:750").is_err());
+        assert!(parse_steps("11:750:legend").is_err());
+        assert!(parse_steps("11:750:uber:uber").is_err());
+    }
+
+    #[test]
+    fn end_seed_skips_one_advance_for_guaranteed_ubers() {
+        let seed: u32 = 42;
+        let twice: u32 = advance_seed(advance_seed(seed));
+        assert_eq!(get_end_seed(seed, &[(0, 1)]), twice);
+        assert_eq!(
+            get_end_seed(seed, &[(GUARANTEED_UBER, IGNORE_SLOT)]),
+            advance_seed(seed)
+        );
+        assert_eq!(
+            get_pattern_entry(1, 3, true),
+            (GUARANTEED_UBER, IGNORE_SLOT)
+        );
+        assert_eq!(get_pattern_entry(2, 3, true), (GUARANTEED_UBER, 3));
+        assert_eq!(get_pattern_entry(2, 3, false), (2, 3));
+    }
+
+    #[test]
+    fn matches_patterns_from_a_seed() {
+        let event: GatyaEvent = get_test_event(false, GatyaFlags::empty());
+        let slots: Vec<Vec<i32>> = get_test_slots();
+        // cats 4 and 13 are slot 1 of the rares and slot 3 of the super rares
+        let cats: [(u32, u32); 3] = [(0, 1), (1, 3), (BLANK_SLOT, 0)];
+        assert!(matches_pattern(
+            123456789,
+            &event,
+            &slots,
+            &cats,
+            &DrawMode::Single
+        ));
+        assert!(matches_pattern(
+            123456789,
+            &event,
+            &slots,
+            &[(0, IGNORE_SLOT)],
+            &DrawMode::Single
+        ));
+        assert!(!matches_pattern(
+            123456789,
+            &event,
+            &slots,
+            &[(0, 2)],
+            &DrawMode::Single
+        ));
+        assert!(!matches_pattern(
+            123456789,
+            &event,
+            &slots,
+            &[(1, IGNORE_SLOT)],
+            &DrawMode::Single
+        ));
+    }
+}
@@ -0,0 +1,245 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use sha2::{Digest, Sha256};
+
+use crate::cache;
+use crate::error;
+use crate::gatya_data::{self, GatyaEvent};
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub taken_at: NaiveDateTime,
+}
+
+fn get_snapshot_dir() -> PathBuf {
+    cache::get_data_dir().join("snapshots")
+}
+
+fn get_content_hash(data: &str) -> String {
+    let hash = Sha256::digest(data.as_bytes());
+    hex::encode(&hash[..8])
+}
+
+// Snapshots are named gatya_{cc}_{timestamp}_{hash}.tsv so duplicates can be spotted by name
+fn parse_snapshot_name(cc: &str, file_name: &str) -> Option<(NaiveDateTime, String)> {
+    let rest: &str = file_name
+        .strip_prefix(&format!("gatya_{}_", cc))?
+        .strip_suffix(".tsv")?;
+    let (timestamp, hash) = rest.rsplit_once('_')?;
+    let taken_at: NaiveDateTime =
+        NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+    Some((taken_at, hash.to_string()))
+}
+
+fn get_snapshot_entries(snapshot_dir: &Path, cc: &str) -> error::Result<Vec<(Snapshot, String)>> {
+    let mut entries: Vec<(Snapshot, String)> = Vec::new();
+    if !snapshot_dir.exists() {
+        return Ok(entries);
+    }
+    for entry in std::fs::read_dir(snapshot_dir)? {
+        let path: PathBuf = entry?.path();
+        let file_name: String = match path.file_name() {
+            Some(file_name) => file_name.to_string_lossy().to_string(),
+            None => continue,
+        };
+        if let Some((taken_at, hash)) = parse_snapshot_name(cc, &file_name) {
+            entries.push((Snapshot { path, taken_at }, hash));
+        }
+    }
+    entries.sort_by_key(|(snapshot, _)| std::cmp::Reverse(snapshot.taken_at));
+    Ok(entries)
+}
+
+// Newest first
+pub fn list_snapshots(cc: &str) -> error::Result<Vec<Snapshot>> {
+    list_snapshots_in(&get_snapshot_dir(), cc)
+}
+
+fn list_snapshots_in(snapshot_dir: &Path, cc: &str) -> error::Result<Vec<Snapshot>> {
+    Ok(get_snapshot_entries(snapshot_dir, cc)?
+        .into_iter()
+        .map(|(snapshot, _)| snapshot)
+        .collect())
+}
+
+pub fn save_snapshot(cc: &str, data: &str, taken_at: DateTime<Utc>) -> error::Result<bool> {
+    save_snapshot_in(&get_snapshot_dir(), cc, data, taken_at)
+}
+
+fn save_snapshot_in(
+    snapshot_dir: &Path,
+    cc: &str,
+    data: &str,
+    taken_at: DateTime<Utc>,
+) -> error::Result<bool> {
+    let hash: String = get_content_hash(data);
+    if get_snapshot_entries(snapshot_dir, cc)?
+        .iter()
+        .any(|(_, snapshot_hash)| *snapshot_hash == hash)
+    {
+        return Ok(false);
+    }
+    let timestamp: String = taken_at
+        .with_timezone(&Local)
+        .format(TIMESTAMP_FORMAT)
+        .to_string();
+    std::fs::create_dir_all(snapshot_dir)?;
+    std::fs::write(
+        snapshot_dir.join(format!("gatya_{}_{}_{}.tsv", cc, timestamp, hash)),
+        data,
+    )?;
+    Ok(true)
+}
+
+pub fn read_snapshot(snapshot: &Snapshot) -> error::Result<String> {
+    Ok(std::fs::read_to_string(&snapshot.path)?)
+}
+
+fn get_unique_events(event_lists: Vec<Vec<GatyaEvent>>) -> Vec<GatyaEvent> {
+    let mut gatya_events: Vec<GatyaEvent> = Vec::new();
+    for gatya_event in event_lists.into_iter().flatten() {
+        let duplicate: bool = gatya_events.iter().any(|other| {
+            other.gatya_id == gatya_event.gatya_id
+                && other.start == gatya_event.start
+                && other.end == gatya_event.end
+                && other.banner_txt == gatya_event.banner_txt
+        });
+        if !duplicate {
+            gatya_events.push(gatya_event);
+        }
+    }
+    gatya_events
+}
+
+// The current events followed by any older ones only found in snapshots
+pub fn get_all_events(cc: &str, data: String) -> error::Result<Vec<GatyaEvent>> {
+    get_all_events_in(&get_snapshot_dir(), cc, data)
+}
+
+// A snapshot that can't be read or parsed is skipped so the others are still shown
+fn get_all_events_in(
+    snapshot_dir: &Path,
+    cc: &str,
+    data: String,
+) -> error::Result<Vec<GatyaEvent>> {
+    let mut event_lists: Vec<Vec<GatyaEvent>> = vec![gatya_data::parse_gatya_events(data)?];
+    for snapshot in list_snapshots_in(snapshot_dir, cc)?.iter() {
+        match read_snapshot(snapshot).and_then(gatya_data::parse_gatya_events) {
+            Ok(gatya_events) => event_lists.push(gatya_events),
+            Err(err) => eprintln!("Skipping snapshot {}: {}", snapshot.path.display(), err),
+        }
+    }
+    Ok(get_unique_events(event_lists))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn get_event_data(gatya_ids: &[u32]) -> String {
+        let lines: Vec<String> = gatya_ids
+            .iter()
+            .map(|gatya_id| {
+                format!(
+                    "20240801\t1100\t20240815\t1100\t0\t999999\t0\t0\t1\t1\t{}\t150\t0\t0\t0\t0\t6970\t0\t2500\t0\t500\t0\t30\t0\tBanner {}",
+                    gatya_id, gatya_id
+                )
+            })
+            .collect();
+        format!("[start]\n{}\n[end]\n", lines.join("\n"))
+    }
+
+    fn get_time(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 8, 1, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn round_trips_snapshot_names() {
+        let taken_at: NaiveDateTime = get_time(11).naive_utc();
+        let file_name: String = format!(
+            "gatya_en_{}_0123456789abcdef.tsv",
+            taken_at.format(TIMESTAMP_FORMAT)
+        );
+        assert_eq!(
+            parse_snapshot_name("en", &file_name),
+            Some((taken_at, "0123456789abcdef".to_string()))
+        );
+        assert_eq!(parse_snapshot_name("jp", &file_name), None);
+        assert_eq!(
+            parse_snapshot_name("en", "gatya_en_yesterday_0123.tsv"),
+            None
+        );
+        assert_eq!(parse_snapshot_name("en", "gatya_en.tsv"), None);
+        assert_eq!(
+            parse_snapshot_name("en", &file_name.replace(".tsv", ".txt")),
+            None
+        );
+    }
+
+    #[test]
+    fn saves_each_version_once_and_lists_newest_first() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let snapshot_dir: PathBuf = dir.path().join("snapshots");
+        assert!(list_snapshots_in(&snapshot_dir, "en").unwrap().is_empty());
+
+        let old_data: String = get_event_data(&[905]);
+        let new_data: String = get_event_data(&[905, 906]);
+        assert!(save_snapshot_in(&snapshot_dir, "en", &old_data, get_time(1)).unwrap());
+        assert!(!save_snapshot_in(&snapshot_dir, "en", &old_data, get_time(2)).unwrap());
+        assert!(save_snapshot_in(&snapshot_dir, "en", &new_data, get_time(3)).unwrap());
+        // other regions keep their own snapshots
+        assert!(save_snapshot_in(&snapshot_dir, "jp", &old_data, get_time(4)).unwrap());
+
+        let snapshots: Vec<Snapshot> = list_snapshots_in(&snapshot_dir, "en").unwrap();
+        let taken_at: Vec<NaiveDateTime> =
+            snapshots.iter().map(|snapshot| snapshot.taken_at).collect();
+        assert_eq!(
+            taken_at,
+            vec![
+                get_time(3).with_timezone(&Local).naive_local(),
+                get_time(1).with_timezone(&Local).naive_local()
+            ]
+        );
+        assert_eq!(read_snapshot(&snapshots[0]).unwrap(), new_data);
+        let file_name: String = snapshots[1]
+            .path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let (_, hash) = parse_snapshot_name("en", &file_name).unwrap();
+        assert_eq!(hash, get_content_hash(&old_data));
+    }
+
+    #[test]
+    fn merges_snapshot_events_and_skips_unreadable_snapshots() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let snapshot_dir: PathBuf = dir.path().join("snapshots");
+        save_snapshot_in(
+            &snapshot_dir,
+            "en",
+            &get_event_data(&[904, 905]),
+            get_time(1),
+        )
+        .unwrap();
+        save_snapshot_in(&snapshot_dir, "en", &get_event_data(&[903]), get_time(2)).unwrap();
+        std::fs::write(
+            snapshot_dir.join("gatya_en_20240801-050000_0123456789abcdef.tsv"),
+            [0xff, 0xfe, 0x00],
+        )
+        .unwrap();
+
+        let gatya_events: Vec<GatyaEvent> =
+            get_all_events_in(&snapshot_dir, "en", get_event_data(&[905, 906])).unwrap();
+        let gatya_ids: Vec<u32> = gatya_events
+            .iter()
+            .map(|gatya_event| gatya_event.gatya_id)
+            .collect();
+        assert_eq!(gatya_ids, vec![905, 906, 903, 904]);
+    }
+}
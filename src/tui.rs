@@ -0,0 +1,941 @@
+use chrono::{Local, NaiveDateTime};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Gauge, List, ListItem, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crate::gatya_data::{self, EventStatus, GatyaEvent};
+use crate::simulator::{self, DrawMode};
+use crate::{cli, error, Banner, NEXT_ROLLS};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const PAGE_SIZE: i32 = 10;
+
+// What find_banner_seeds returns: the searched pattern, the seeds and how long it took
+type FoundSeeds = (Vec<(u32, u32)>, Vec<u32>, Duration);
+
+enum Screen {
+    Events,
+    Cats,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Pool,
+    Seeds,
+}
+
+struct RunningSearch {
+    progress: Arc<AtomicU64>,
+    handle: JoinHandle<FoundSeeds>,
+}
+
+struct SearchResult {
+    cats: Vec<(u32, u32)>,
+    seeds: Vec<u32>,
+}
+
+struct App {
+    cc: String,
+    offline: bool,
+    // --update, cleared once a banner's game data has been downloaded
+    update: bool,
+    thread_count: u32,
+    steps: Option<Vec<simulator::Step>>,
+    now: NaiveDateTime,
+    screen: Screen,
+    events: Vec<GatyaEvent>,
+    event_filter: String,
+    event_state: ListState,
+    editing_filter: bool,
+    banner: Option<Arc<Banner>>,
+    draw_modes: Vec<(String, DrawMode)>,
+    draw_index: usize,
+    cat_filter: String,
+    cat_state: ListState,
+    cats_ids: Vec<i32>,
+    focus: Focus,
+    running: Option<RunningSearch>,
+    result: Option<SearchResult>,
+    seed_state: ListState,
+    status: String,
+    quit: bool,
+}
+
+fn move_selection(state: &mut ListState, len: usize, delta: i32) {
+    if len == 0 {
+        state.select(None);
+        return;
+    }
+    let current: i32 = state.selected().unwrap_or(0) as i32;
+    let next: i32 = (current + delta).clamp(0, len as i32 - 1);
+    state.select(Some(next as usize));
+}
+
+// Step-up banners are always rolled as step-ups, like in the prompts. Without --steps the
+// layout is only assumed, which the labels point out.
+fn get_draw_modes(
+    gatya_event: &GatyaEvent,
+    total_uber_rares: usize,
+    steps: &Option<Vec<simulator::Step>>,
+) -> Vec<(String, DrawMode)> {
+    if gatya_event.flags.contains(gatya_data::GatyaFlags::STEP_UP) && total_uber_rares > 0 {
+        let assumed: &str = if steps.is_none() { ", assumed" } else { "" };
+        let steps: Vec<simulator::Step> =
+            steps.clone().unwrap_or_else(simulator::get_default_steps);
+        return steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                (
+                    format!(
+                        "Step-up from step {} ({} rolls, {} cat food{})",
+                        i + 1,
+                        step.rolls,
+                        step.price,
+                        assumed
+                    ),
+                    DrawMode::StepUp(simulator::StepUp::new(steps.clone(), i)),
+                )
+            })
+            .collect();
+    }
+    let mut draw_modes: Vec<(String, DrawMode)> =
+        vec![("Single draws".to_string(), DrawMode::Single)];
+    if gatya_event.guaranteed && total_uber_rares > 0 {
+        draw_modes.push(("11 draws".to_string(), DrawMode::ElevenDraws));
+    }
+    draw_modes
+}
+
+fn get_status_style(status: EventStatus) -> Style {
+    match status {
+        EventStatus::Running => Style::new().fg(Color::Green),
+        EventStatus::Upcoming => Style::new().fg(Color::Yellow),
+        EventStatus::Past => Style::new().fg(Color::DarkGray),
+    }
+}
+
+fn get_chance_display(chance: u32) -> String {
+    format!("{:.2}%", chance as f64 / 100.0)
+}
+
+impl App {
+    fn new(
+        cc: String,
+        offline: bool,
+        update: bool,
+        thread_count: u32,
+        steps: Option<Vec<simulator::Step>>,
+        events: Vec<GatyaEvent>,
+    ) -> App {
+        App {
+            cc,
+            offline,
+            update,
+            thread_count,
+            steps,
+            now: Local::now().naive_local(),
+            screen: Screen::Events,
+            events,
+            event_filter: String::new(),
+            event_state: ListState::default().with_selected(Some(0)),
+            editing_filter: false,
+            banner: None,
+            draw_modes: Vec::new(),
+            draw_index: 0,
+            cat_filter: String::new(),
+            cat_state: ListState::default(),
+            cats_ids: Vec::new(),
+            focus: Focus::Pool,
+            running: None,
+            result: None,
+            seed_state: ListState::default(),
+            status: String::new(),
+            quit: false,
+        }
+    }
+
+    fn get_visible_events(&self) -> Vec<usize> {
+        let filter: gatya_data::EventFilter = gatya_data::EventFilter {
+            text: Some(self.event_filter.clone()),
+            ..Default::default()
+        };
+        self.events
+            .iter()
+            .enumerate()
+            .filter(|(_, gatya_event)| gatya_event.matches(&filter, self.now))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    // Pool entries as (rarity, number in the pool, cat id), numbered like the #numbers in the prompts
+    fn get_visible_cats(&self) -> Vec<(u32, usize, i32)> {
+        let banner: &Banner = match &self.banner {
+            Some(banner) => banner,
+            None => return Vec::new(),
+        };
+        let filter: String = self.cat_filter.to_lowercase();
+        let mut cats: Vec<(u32, usize, i32)> = Vec::new();
+        let mut counter: usize = 0;
+        for (rarity, rarity_data) in banner.gatya_slot_data.iter().enumerate() {
+            for cat_id in rarity_data.iter() {
+                counter += 1;
+                let display: String = crate::cat_names::get_cat_display(*cat_id, &banner.cat_names);
+                if display.to_lowercase().contains(&filter) {
+                    cats.push((rarity as u32, counter, *cat_id));
+                }
+            }
+        }
+        cats
+    }
+
+    fn get_draw_mode(&self) -> &DrawMode {
+        &self.draw_modes[self.draw_index].1
+    }
+
+    fn get_pattern(&self, banner: &Banner) -> Vec<(u32, u32)> {
+        let cats: Vec<(u32, u32)> = gatya_data::get_cat_list_from_ids(
+            banner.gatya_slot_data.clone(),
+            self.cats_ids.clone(),
+        )
+        .unwrap_or_default();
+        crate::get_search_pattern(&banner.gatya_event, cats, self.get_draw_mode())
+    }
+
+    // Seeds from the last search that still match every cat entered since
+    fn get_matching_seeds(&self) -> Vec<u32> {
+        let (banner, result) = match (&self.banner, &self.result) {
+            (Some(banner), Some(result)) => (banner, result),
+            _ => return Vec::new(),
+        };
+        let cats: Vec<(u32, u32)> = self.get_pattern(banner);
+        result
+            .seeds
+            .iter()
+            .copied()
+            .filter(|seed| {
+                simulator::matches_pattern(
+                    *seed,
+                    &banner.gatya_event,
+                    &banner.gatya_slot_data,
+                    &cats,
+                    self.get_draw_mode(),
+                )
+            })
+            .collect()
+    }
+
+    async fn select_event(&mut self, terminal: &mut DefaultTerminal) -> error::Result<()> {
+        let visible: Vec<usize> = self.get_visible_events();
+        let index: usize = match self.event_state.selected().and_then(|i| visible.get(i)) {
+            Some(index) => *index,
+            None => return Ok(()),
+        };
+        let gatya_event: GatyaEvent = self.events[index].clone();
+        self.status = format!("Loading {}...", gatya_event.banner_txt);
+        terminal.draw(|frame| self.draw(frame))?;
+        let loaded: error::Result<Banner> =
+            crate::load_banner(&self.cc, gatya_event, self.offline, self.update).await;
+        // loading can log to stderr, which would be left on screen
+        terminal.clear()?;
+        let banner: Banner = match loaded {
+            Ok(banner) => banner,
+            Err(err) => {
+                self.status = err.to_string();
+                return Ok(());
+            }
+        };
+        // the game data is shared by every banner, so later ones use the fresh copy
+        self.update = false;
+        self.draw_modes = get_draw_modes(
+            &banner.gatya_event,
+            banner.gatya_slot_data[2].len(),
+            &self.steps,
+        );
+        self.draw_index = 0;
+        self.status = format!("Selected event: {}", banner.gatya_event.banner_txt);
+        self.banner = Some(Arc::new(banner));
+        self.cat_filter.clear();
+        self.cat_state.select(Some(0));
+        self.cats_ids.clear();
+        self.result = None;
+        self.focus = Focus::Pool;
+        self.screen = Screen::Cats;
+        Ok(())
+    }
+
+    fn add_cat(&mut self, cat_id: i32) {
+        self.cats_ids.push(cat_id);
+        self.seed_state.select(Some(0));
+        let banner: &Banner = match &self.banner {
+            Some(banner) => banner,
+            None => return,
+        };
+        self.status = format!(
+            "Added {}",
+            crate::get_cat_entry_display(cat_id, &banner.cat_names)
+        );
+        if self.result.is_some() && self.get_matching_seeds().is_empty() {
+            self.status = "No seed from the last search matches these cats".to_string();
+        }
+    }
+
+    fn start_search(&mut self) {
+        if self.running.is_some() {
+            return;
+        }
+        let banner: Arc<Banner> = match &self.banner {
+            Some(banner) => banner.clone(),
+            None => return,
+        };
+        if self.cats_ids.is_empty() {
+            self.status = "Add the cats you rolled first".to_string();
+            return;
+        }
+        let cats: Vec<(u32, u32)> = match gatya_data::get_cat_list_from_ids(
+            banner.gatya_slot_data.clone(),
+            self.cats_ids.clone(),
+        ) {
+            Ok(cats) => cats,
+            Err(err) => {
+                self.status = err.to_string();
+                return;
+            }
+        };
+        self.status = if crate::is_collisions(cats.clone(), banner.gatya_slot_data[0].len() as u32)
+        {
+            "WARNING: There might be a duplicate rare cat! The seed might not be found.".to_string()
+        } else {
+            String::new()
+        };
+        let draw_mode: DrawMode = self.get_draw_mode().clone();
+        let thread_count: u32 = self.thread_count;
+        let progress: Arc<AtomicU64> = Arc::new(AtomicU64::new(0));
+        let thread_progress: Arc<AtomicU64> = progress.clone();
+        let handle: JoinHandle<FoundSeeds> = std::thread::spawn(move || {
+            crate::find_banner_seeds(&banner, cats, &draw_mode, thread_count, thread_progress)
+        });
+        self.running = Some(RunningSearch { progress, handle });
+        self.result = None;
+    }
+
+    fn check_search(&mut self) {
+        let finished: bool = match &self.running {
+            Some(running) => running.handle.is_finished(),
+            None => false,
+        };
+        if !finished {
+            return;
+        }
+        let running: RunningSearch = match self.running.take() {
+            Some(running) => running,
+            None => return,
+        };
+        match running.handle.join() {
+            Ok((cats, seeds, duration)) => {
+                self.status = match seeds.len() {
+                    0 => format!(
+                        "Seed not found ({:?}). Check the cats and try again.",
+                        duration
+                    ),
+                    1 => format!("Found the seed in {:?}", duration),
+                    count => format!(
+                        "Found {} seeds in {:?}, add more cats to narrow them down",
+                        count, duration
+                    ),
+                };
+                self.result = Some(SearchResult { cats, seeds });
+                self.seed_state.select(Some(0));
+            }
+            Err(_) => self.status = "The search stopped unexpectedly".to_string(),
+        }
+    }
+
+    fn handle_filter_key(&mut self, code: KeyCode) {
+        let filter: &mut String = match self.screen {
+            Screen::Events => &mut self.event_filter,
+            Screen::Cats => &mut self.cat_filter,
+        };
+        match code {
+            KeyCode::Enter | KeyCode::Esc => self.editing_filter = false,
+            KeyCode::Backspace => {
+                filter.pop();
+            }
+            KeyCode::Char(c) => filter.push(c),
+            _ => return,
+        }
+        match self.screen {
+            Screen::Events => self.event_state.select(Some(0)),
+            Screen::Cats => self.cat_state.select(Some(0)),
+        }
+    }
+
+    async fn handle_events_key(
+        &mut self,
+        code: KeyCode,
+        terminal: &mut DefaultTerminal,
+    ) -> error::Result<()> {
+        let len: usize = self.get_visible_events().len();
+        match code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Up => move_selection(&mut self.event_state, len, -1),
+            KeyCode::Down => move_selection(&mut self.event_state, len, 1),
+            KeyCode::PageUp => move_selection(&mut self.event_state, len, -PAGE_SIZE),
+            KeyCode::PageDown => move_selection(&mut self.event_state, len, PAGE_SIZE),
+            KeyCode::Enter => self.select_event(terminal).await?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_cats_key(&mut self, code: KeyCode) {
+        let (state, len): (&mut ListState, usize) = match self.focus {
+            Focus::Pool => {
+                let len: usize = self.get_visible_cats().len();
+                (&mut self.cat_state, len)
+            }
+            Focus::Seeds => {
+                let len: usize = self.get_matching_seeds().len();
+                (&mut self.seed_state, len)
+            }
+        };
+        match code {
+            KeyCode::Up => return move_selection(state, len, -1),
+            KeyCode::Down => return move_selection(state, len, 1),
+            KeyCode::PageUp => return move_selection(state, len, -PAGE_SIZE),
+            KeyCode::PageDown => return move_selection(state, len, PAGE_SIZE),
+            _ => {}
+        }
+        match code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Char('/') => {
+                self.focus = Focus::Pool;
+                self.editing_filter = true;
+            }
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Pool => Focus::Seeds,
+                    Focus::Seeds => Focus::Pool,
+                }
+            }
+            KeyCode::Enter if self.focus == Focus::Pool => {
+                let cats: Vec<(u32, usize, i32)> = self.get_visible_cats();
+                if let Some((_, _, cat_id)) = self.cat_state.selected().and_then(|i| cats.get(i)) {
+                    self.add_cat(*cat_id);
+                }
+            }
+            KeyCode::Char('b') => self.add_cat(crate::BLANK_SLOT_USER),
+            KeyCode::Backspace => {
+                self.cats_ids.pop();
+                self.seed_state.select(Some(0));
+            }
+            KeyCode::Char('c') => {
+                self.cats_ids.clear();
+                self.result = None;
+            }
+            KeyCode::Char('d') if self.running.is_some() => {
+                self.status = "Wait for the search to finish first".to_string();
+            }
+            KeyCode::Char('d') => {
+                self.draw_index = (self.draw_index + 1) % self.draw_modes.len();
+                // seeds found with another draw mode can't be checked against this one
+                self.result = None;
+            }
+            KeyCode::Char('s') => self.start_search(),
+            KeyCode::Esc if self.running.is_some() => {
+                self.status = "Wait for the search to finish first".to_string();
+            }
+            KeyCode::Esc => {
+                self.screen = Screen::Events;
+                self.status.clear();
+            }
+            _ => {}
+        }
+    }
+
+    async fn handle_key(
+        &mut self,
+        code: KeyCode,
+        terminal: &mut DefaultTerminal,
+    ) -> error::Result<()> {
+        if self.editing_filter {
+            self.handle_filter_key(code);
+            return Ok(());
+        }
+        match self.screen {
+            Screen::Events => self.handle_events_key(code, terminal).await?,
+            Screen::Cats => self.handle_cats_key(code),
+        }
+        Ok(())
+    }
+
+    async fn run(&mut self, terminal: &mut DefaultTerminal) -> error::Result<()> {
+        while !self.quit {
+            self.check_search();
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(Duration::from_millis(100))? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key.code, terminal).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main_area, status_area, help_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        match self.screen {
+            Screen::Events => self.draw_events(frame, main_area),
+            Screen::Cats => self.draw_cats(frame, main_area),
+        }
+        frame.render_widget(
+            Paragraph::new(self.status.as_str()).style(Style::new().fg(Color::Yellow)),
+            status_area,
+        );
+        let help: &str = if self.editing_filter {
+            "type to filter  enter/esc: done"
+        } else {
+            match self.screen {
+                Screen::Events => "up/down: move  enter: open banner  /: filter  q: quit",
+                Screen::Cats => {
+                    "enter: add cat  b: blank  backspace: remove last  c: clear  d: draw mode  s: search  tab: seeds  /: filter  esc: events  q: quit"
+                }
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(help).style(Style::new().fg(Color::DarkGray)),
+            help_area,
+        );
+    }
+
+    fn get_filter_title(&self, title: &str, filter: &str) -> String {
+        if filter.is_empty() && !self.editing_filter {
+            title.to_string()
+        } else {
+            format!("{} - filter: {}", title, filter)
+        }
+    }
+
+    fn draw_events(&mut self, frame: &mut Frame, area: Rect) {
+        let [list_area, details_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(6)]).areas(area);
+        let visible: Vec<usize> = self.get_visible_events();
+        let items: Vec<ListItem> = visible
+            .iter()
+            .map(|i| {
+                let gatya_event: &GatyaEvent = &self.events[*i];
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!(
+                            "{} - {}  ",
+                            gatya_event.start.format(DATE_FORMAT),
+                            gatya_event.end.format(DATE_FORMAT)
+                        ),
+                        get_status_style(gatya_event.get_status(self.now)),
+                    ),
+                    Span::raw(gatya_event.banner_txt.clone()),
+                    Span::styled(
+                        format!(" (id {})", gatya_event.gatya_id),
+                        Style::new().fg(Color::DarkGray),
+                    ),
+                ]))
+            })
+            .collect();
+        let title: String = self.get_filter_title(
+            &format!("Events ({}, {} shown)", self.cc, visible.len()),
+            &self.event_filter,
+        );
+        let list: List = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.event_state);
+
+        let selected: Option<&GatyaEvent> = self
+            .event_state
+            .selected()
+            .and_then(|i| visible.get(i))
+            .map(|i| &self.events[*i]);
+        let lines: Vec<Line> = match selected {
+            Some(gatya_event) => {
+                let tags: Vec<String> = gatya_event.get_tags();
+                vec![
+                    Line::from(Span::styled(
+                        gatya_event.banner_txt.clone(),
+                        Style::new().add_modifier(Modifier::BOLD),
+                    )),
+                    Line::from(format!(
+                        "{} - {}, gatya id {}, price {}",
+                        gatya_event.start.format("%Y-%m-%d %H:%M"),
+                        gatya_event.end.format("%Y-%m-%d %H:%M"),
+                        gatya_event.gatya_id,
+                        gatya_event.price
+                    )),
+                    Line::from(format!(
+                        "Rare {}, Super Rare {}, Uber Rare {}, Legend Rare {}",
+                        get_chance_display(gatya_event.rare_chance),
+                        get_chance_display(gatya_event.super_rare_chance),
+                        get_chance_display(gatya_event.uber_rare_chance),
+                        get_chance_display(gatya_event.legend_rare_chance)
+                    )),
+                    Line::from(tags.join(", ")),
+                ]
+            }
+            None => vec![Line::from("No events match the filter")],
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Details")),
+            details_area,
+        );
+    }
+
+    fn draw_cats(&mut self, frame: &mut Frame, area: Rect) {
+        let banner: Arc<Banner> = match &self.banner {
+            Some(banner) => banner.clone(),
+            None => return,
+        };
+        let [pool_area, right_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(area);
+        let [pattern_area, progress_area, results_area] = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ])
+        .areas(right_area);
+        let [seeds_area, rolls_area] =
+            Layout::horizontal([Constraint::Length(16), Constraint::Min(0)]).areas(results_area);
+
+        let focused: Style = Style::new().fg(Color::Cyan);
+        let get_block_style = |focus: Focus| {
+            if self.focus == focus {
+                focused
+            } else {
+                Style::new()
+            }
+        };
+
+        let visible: Vec<(u32, usize, i32)> = self.get_visible_cats();
+        let items: Vec<ListItem> = visible
+            .iter()
+            .map(|(rarity, number, cat_id)| {
+                ListItem::new(format!(
+                    "{:<12} #{} {}",
+                    simulator::get_rarity_name(*rarity),
+                    number,
+                    crate::cat_names::get_cat_display(*cat_id, &banner.cat_names)
+                ))
+            })
+            .collect();
+        let title: String = self.get_filter_title(
+            &format!("{} pool", banner.gatya_event.banner_txt),
+            &self.cat_filter,
+        );
+        let list: List = List::new(items)
+            .block(
+                Block::bordered()
+                    .title(title)
+                    .border_style(get_block_style(Focus::Pool)),
+            )
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, pool_area, &mut self.cat_state);
+
+        let entries: Vec<String> = self
+            .cats_ids
+            .iter()
+            .map(|cat_id| crate::get_cat_entry_display(*cat_id, &banner.cat_names))
+            .collect();
+        let pattern_text: String = if entries.is_empty() {
+            "Add the cats you rolled, in order".to_string()
+        } else {
+            entries.join(" > ")
+        };
+        frame.render_widget(
+            Paragraph::new(pattern_text)
+                .wrap(Wrap { trim: true })
+                .block(Block::bordered().title(format!(
+                    "Your cats ({}) - {}",
+                    self.cats_ids.len(),
+                    self.draw_modes[self.draw_index].0
+                ))),
+            pattern_area,
+        );
+
+        match &self.running {
+            Some(running) => {
+                let ratio: f64 =
+                    (running.progress.load(Ordering::Relaxed) as f64 / u32::MAX as f64).min(1.0);
+                frame.render_widget(
+                    Gauge::default()
+                        .block(
+                            Block::bordered()
+                                .title(format!("Searching with {} threads", self.thread_count)),
+                        )
+                        .gauge_style(Style::new().fg(Color::Green))
+                        .ratio(ratio),
+                    progress_area,
+                );
+            }
+            None => {
+                let text: String = match &self.result {
+                    Some(result) if self.cats_ids.len() < result.cats.len() => {
+                        "Fewer cats than the last search, press s to search again".to_string()
+                    }
+                    Some(_) => "Add the cats you roll next to narrow down the seeds".to_string(),
+                    None => "Press s to search".to_string(),
+                };
+                frame.render_widget(
+                    Paragraph::new(text).block(Block::bordered().title("Search")),
+                    progress_area,
+                );
+            }
+        }
+
+        let seeds: Vec<u32> = self.get_matching_seeds();
+        let seed_items: Vec<ListItem> = seeds
+            .iter()
+            .map(|seed| ListItem::new(seed.to_string()))
+            .collect();
+        let seed_list: List = List::new(seed_items)
+            .block(
+                Block::bordered()
+                    .title(format!("Seeds ({})", seeds.len()))
+                    .border_style(get_block_style(Focus::Seeds)),
+            )
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(seed_list, seeds_area, &mut self.seed_state);
+
+        let selected_seed: Option<u32> = self
+            .seed_state
+            .selected()
+            .and_then(|i| seeds.get(i))
+            .copied();
+        let lines: Vec<Line> = match selected_seed {
+            Some(seed) => get_next_roll_lines(
+                seed,
+                &self.get_pattern(&banner),
+                &banner,
+                self.get_draw_mode(),
+                &banner.cat_names,
+            ),
+            None => Vec::new(),
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Next rolls")),
+            rolls_area,
+        );
+    }
+}
+
+fn get_next_roll_lines(
+    seed: u32,
+    cats: &[(u32, u32)],
+    banner: &Banner,
+    draw_mode: &DrawMode,
+    cat_names: &HashMap<i32, String>,
+) -> Vec<Line<'static>> {
+    let end_seed: u32 = simulator::get_end_seed(seed, cats);
+    let (rolls, _) = simulator::simulate_rolls(
+        end_seed,
+        &banner.gatya_event,
+        &banner.gatya_slot_data,
+        cats.len(),
+        NEXT_ROLLS,
+        draw_mode,
+    );
+    let mut lines: Vec<Line> = vec![Line::from(Span::styled(
+        format!("Seed after your cats: {}", end_seed),
+        Style::new().fg(Color::DarkGray),
+    ))];
+    let mut last_step: Option<usize> = None;
+    for (i, roll) in rolls.iter().enumerate() {
+        if let (DrawMode::StepUp(step_up), Some(step)) = (draw_mode, roll.step) {
+            if last_step != Some(step) {
+                lines.push(Line::from(format!(
+                    "Step {} ({} cat food):",
+                    step + 1,
+                    step_up.steps[step].price
+                )));
+                last_step = Some(step);
+            }
+        }
+        let style: Style = if roll.rarity >= 2 {
+            Style::new().fg(Color::Yellow)
+        } else {
+            Style::new()
+        };
+        lines.push(Line::from(Span::styled(
+            crate::get_roll_display(roll, cats.len() + i + 1, cat_names),
+            style,
+        )));
+    }
+    lines
+}
+
+pub async fn run_tui(args: &cli::TuiArgs, offline: bool) -> error::Result<()> {
+    let cc: String = crate::get_cc(&args.region)?;
+    let steps: Option<Vec<simulator::Step>> = match &args.steps {
+        Some(steps) => Some(simulator::parse_steps(steps).map_err(error::Error::InvalidInput)?),
+        None => None,
+    };
+    let data: String = crate::load_event_data(&cc, offline, args.update).await?;
+    let events: Vec<GatyaEvent> = gatya_data::parse_gatya_events(data)?
+        .into_iter()
+        .filter(|gatya_event| !gatya_event.banner_txt.is_empty())
+        .collect();
+    if events.is_empty() {
+        return Err(error::Error::InvalidInput(
+            "there are no events with a banner".to_string(),
+        ));
+    }
+    let mut app: App = App::new(
+        cc,
+        offline,
+        args.update,
+        args.threads
+            .unwrap_or_else(crate::get_default_thread_count)
+            .max(1),
+        steps,
+        events,
+    );
+    let mut terminal: DefaultTerminal = ratatui::init();
+    let result: error::Result<()> = app.run(&mut terminal).await;
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gatya_data::GatyaFlags;
+    use crate::mock_server::tests::get_test_event;
+
+    fn get_draw_mode_names(draw_modes: &[(String, DrawMode)]) -> Vec<&str> {
+        draw_modes.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn offers_the_draw_modes_the_banner_has() {
+        let event: GatyaEvent = get_test_event(false, GatyaFlags::empty());
+        assert_eq!(
+            get_draw_mode_names(&get_draw_modes(&event, 4, &None)),
+            vec!["Single draws"]
+        );
+        let event: GatyaEvent = get_test_event(true, GatyaFlags::empty());
+        assert_eq!(
+            get_draw_mode_names(&get_draw_modes(&event, 4, &None)),
+            vec!["Single draws", "11 draws"]
+        );
+        assert_eq!(get_draw_modes(&event, 0, &None).len(), 1);
+
+        let event: GatyaEvent = get_test_event(false, GatyaFlags::STEP_UP);
+        let draw_modes: Vec<(String, DrawMode)> = get_draw_modes(&event, 4, &None);
+        assert_eq!(draw_modes.len(), 4);
+        assert_eq!(
+            draw_modes[3].0,
+            "Step-up from step 4 (11 rolls, 1500 cat food, assumed)"
+        );
+        assert!(matches!(&draw_modes[3].1, DrawMode::StepUp(step_up) if step_up.current_step == 3));
+        let steps: Option<Vec<simulator::Step>> =
+            Some(simulator::parse_steps("5:300,5:600:uber").unwrap());
+        assert_eq!(
+            get_draw_mode_names(&get_draw_modes(&event, 4, &steps)),
+            vec![
+                "Step-up from step 1 (5 rolls, 300 cat food)",
+                "Step-up from step 2 (5 rolls, 600 cat food)"
+            ]
+        );
+        // without ubers there is nothing for the step-up to guarantee
+        assert_eq!(
+            get_draw_mode_names(&get_draw_modes(&event, 0, &None)),
+            vec!["Single draws"]
+        );
+    }
+
+    #[test]
+    fn moves_the_selection_within_the_list() {
+        let mut state: ListState = ListState::default();
+        move_selection(&mut state, 3, 1);
+        assert_eq!(state.selected(), Some(1));
+        move_selection(&mut state, 3, PAGE_SIZE);
+        assert_eq!(state.selected(), Some(2));
+        move_selection(&mut state, 3, -PAGE_SIZE);
+        assert_eq!(state.selected(), Some(0));
+        move_selection(&mut state, 0, 1);
+        assert_eq!(state.selected(), None);
+    }
+
+    #[test]
+    fn numbers_cats_like_the_pool_list() {
+        let gatya_slot_data: Vec<Vec<i32>> = vec![vec![0, 4], vec![1], vec![], vec![3, 7]];
+        let cat_names: HashMap<i32, String> = HashMap::from([
+            (4, "Tank Cat".to_string()),
+            (3, "Dark Cat".to_string()),
+            (7, "Tank Dragon".to_string()),
+        ]);
+        let mut app: App = App::new(
+            "en".to_string(),
+            true,
+            false,
+            1,
+            None,
+            vec![get_test_event(false, GatyaFlags::empty())],
+        );
+        app.banner = Some(Arc::new(Banner {
+            gatya_event: get_test_event(false, GatyaFlags::empty()),
+            gatya_slot_data: gatya_slot_data.clone(),
+            cat_names: cat_names.clone(),
+        }));
+
+        let cats: Vec<(u32, usize, i32)> = app.get_visible_cats();
+        assert_eq!(
+            cats,
+            vec![(0, 1, 0), (0, 2, 4), (1, 3, 1), (3, 4, 3), (3, 5, 7)]
+        );
+        let pool_lines: Vec<String> = crate::get_cat_pool_lines(&gatya_slot_data, &cat_names);
+        for (_, number, cat_id) in cats.iter() {
+            let line: String = format!(
+                "  #{}. {}",
+                number,
+                crate::cat_names::get_cat_display(*cat_id, &cat_names)
+            );
+            assert!(
+                pool_lines.contains(&line),
+                "{:?} is not in the pool list",
+                line
+            );
+        }
+        // filtering keeps each cat's #number, which is what `#n` refers to everywhere else
+        app.cat_filter = "tank".to_string();
+        let cats: Vec<(u32, usize, i32)> = app.get_visible_cats();
+        assert_eq!(cats, vec![(0, 2, 4), (3, 5, 7)]);
+        for (_, number, cat_id) in cats.iter() {
+            let inputs: Vec<String> = vec![format!("#{}", number)];
+            let parsed: Vec<i32> =
+                crate::parse_cats(&inputs, &gatya_slot_data, &cat_names).unwrap();
+            assert_eq!(parsed, vec![*cat_id]);
+        }
+    }
+}